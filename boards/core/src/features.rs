@@ -3,9 +3,9 @@
 //! Boards opt-in to features by implementing these traits and returning
 //! `Some(self)` from the corresponding `as_*()` method in the Board trait.
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Local, Timelike};
 
-use crate::ScreenPosition;
+use crate::{ScreenPosition, WeatherIcon};
 
 /// Errors that can occur during board operations
 #[derive(Debug, thiserror::Error)]
@@ -22,6 +22,10 @@ pub enum BoardError {
     #[error("invalid screen position: {0}")]
     InvalidScreenPosition(String),
 
+    /// Invalid screen theme
+    #[error("invalid screen theme: {0}")]
+    InvalidTheme(String),
+
     /// Invalid media data
     #[error("invalid media: {0}")]
     InvalidMedia(&'static str),
@@ -43,13 +47,39 @@ pub type Result<T> = std::result::Result<T, BoardError>;
 
 /// Time synchronization capability
 pub trait HasTime {
-    fn set_time(&mut self, time: DateTime<Local>, use_12hr: bool) -> Result<()>;
+    /// Set the clock to the wall time of the given time. Any timezone converts with
+    /// `DateTime::fixed_offset`, ie to show a world clock.
+    fn set_time(&mut self, time: DateTime<FixedOffset>, use_12hr: bool) -> Result<()>;
+
+    /// Show raw values in the hour and minute fields of the clock, ie minutes and seconds for a
+    /// timer. Defaults to setting them as a time today, which limits the hour field to 0-23.
+    fn set_clock_fields(&mut self, hour: u8, minute: u8) -> Result<()> {
+        let time = Local::now()
+            .with_hour(hour.into())
+            .and_then(|t| t.with_minute(minute.into()))
+            .and_then(|t| t.with_second(0))
+            .ok_or(BoardError::CommandFailed("clock fields out of range"))?;
+        self.set_time(time.fixed_offset(), false)
+    }
 }
 
 /// Weather display capability
 pub trait HasWeather {
     /// Set weather display. WMO code is converted to board-specific icon internally.
     fn set_weather(&mut self, wmo: u8, is_day: bool, current: u8, low: u8, high: u8) -> Result<()>;
+
+    /// Set weather display with a specific icon. Defaults to sending a representative
+    /// WMO code for the icon through `set_weather`.
+    fn set_weather_icon(
+        &mut self,
+        icon: WeatherIcon,
+        current: u8,
+        low: u8,
+        high: u8,
+    ) -> Result<()> {
+        let (wmo, is_day) = icon.to_wmo();
+        self.set_weather(wmo, is_day, current, low, high)
+    }
 }
 
 /// System info display capability (CPU temp, GPU temp, download speed)
@@ -67,6 +97,12 @@ pub trait HasScreen {
     fn screen_down(&mut self) -> Result<()>;
     fn screen_switch(&mut self) -> Result<()>;
     fn reset_screen(&mut self) -> Result<()>;
+    /// Set the screen theme by name (e.g., "blue"), for boards that have themes
+    fn set_theme(&mut self, theme: &str) -> Result<()> {
+        Err(BoardError::InvalidTheme(format!(
+            "{theme}, board does not support themes"
+        )))
+    }
 }
 
 /// Screen dimensions - boards with media support should also implement as_screen_size()
@@ -0,0 +1,124 @@
+//! Board-agnostic weather icons.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Weather icons supported by the screen modules
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WeatherIcon {
+    DayClear,
+    DayPartlyCloudy,
+    DayPartlyRainy,
+    NightPartlyCloudy,
+    NightClear,
+    Cloudy,
+    Rainy,
+    Snowfall,
+    Thunderstorm,
+}
+
+impl WeatherIcon {
+    pub const ALL: [WeatherIcon; 9] = [
+        WeatherIcon::DayClear,
+        WeatherIcon::DayPartlyCloudy,
+        WeatherIcon::DayPartlyRainy,
+        WeatherIcon::NightPartlyCloudy,
+        WeatherIcon::NightClear,
+        WeatherIcon::Cloudy,
+        WeatherIcon::Rainy,
+        WeatherIcon::Snowfall,
+        WeatherIcon::Thunderstorm,
+    ];
+
+    /// Convert a WMO index into a weather icon, adapting for day and night
+    /// Adapted from the list at the bottom of <https://open-meteo.com/en/docs>
+    pub fn from_wmo(wmo: u8, is_day: bool) -> Option<Self> {
+        use WeatherIcon::*;
+        match wmo {
+            // clear and mainly clear
+            0 | 1 => Some(if is_day { DayClear } else { NightClear }),
+
+            // partly cloudy
+            2 => Some(if is_day { DayPartlyCloudy } else { NightPartlyCloudy }),
+
+            // overcast
+            3
+            // foggy
+            | 45 | 48 => Some(Cloudy),
+
+            // drizzle
+            51 | 53 | 55
+            // freezing drizzle
+            | 56 | 57
+            // rain
+            | 61 | 63 | 65
+            // freezing rain
+            | 66 | 67 => Some(Rainy),
+
+            // rain showers
+            80..=82 => Some(if is_day { DayPartlyRainy } else { Rainy }),
+
+            // snowfall
+            71 | 73 | 75 | 77
+            // snow showers
+            | 85 | 86 => Some(Snowfall),
+
+            // thunderstorm
+            95 | 96 | 99 => Some(Thunderstorm),
+
+            // unknown
+            _ => None,
+        }
+    }
+
+    /// A representative WMO index and day flag for the icon, the inverse of [`Self::from_wmo`]
+    pub fn to_wmo(&self) -> (u8, bool) {
+        use WeatherIcon::*;
+        match self {
+            DayClear => (0, true),
+            NightClear => (0, false),
+            DayPartlyCloudy => (2, true),
+            NightPartlyCloudy => (2, false),
+            Cloudy => (3, true),
+            Rainy => (61, true),
+            DayPartlyRainy => (80, true),
+            Snowfall => (71, true),
+            Thunderstorm => (95, true),
+        }
+    }
+
+    /// Name used for parsing and display, ie "day-partly-cloudy"
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherIcon::DayClear => "day-clear",
+            WeatherIcon::DayPartlyCloudy => "day-partly-cloudy",
+            WeatherIcon::DayPartlyRainy => "day-partly-rainy",
+            WeatherIcon::NightPartlyCloudy => "night-partly-cloudy",
+            WeatherIcon::NightClear => "night-clear",
+            WeatherIcon::Cloudy => "cloudy",
+            WeatherIcon::Rainy => "rainy",
+            WeatherIcon::Snowfall => "snowfall",
+            WeatherIcon::Thunderstorm => "thunderstorm",
+        }
+    }
+}
+
+impl FromStr for WeatherIcon {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.to_lowercase().replace('_', "-");
+        Self::ALL
+            .into_iter()
+            .find(|icon| icon.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|i| i.name()).collect();
+                format!("invalid icon '{s}', must be one of: {}", names.join(", "))
+            })
+    }
+}
+
+impl Display for WeatherIcon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -7,9 +7,11 @@
 
 mod board;
 mod features;
+mod icon;
 
 pub use board::{Board, BoardInfo, ScreenGroup, ScreenPosition};
 pub use features::{
     BoardError, HasGif, HasImage, HasScreen, HasScreenSize, HasSystemInfo, HasTime, HasWeather,
     Result,
 };
+pub use icon::WeatherIcon;
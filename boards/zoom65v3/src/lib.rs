@@ -3,13 +3,13 @@
 use std::sync::{LazyLock, RwLock};
 
 use checksum::checksum;
-use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
+use chrono::{DateTime, Datelike, FixedOffset, Local, TimeZone, Timelike};
 use float::DumbFloat16;
 use hidapi::{HidApi, HidDevice};
 use types::{Icon, ScreenPosition, ScreenTheme, UploadChannel};
 use zoom_sync_core::{
     Board, BoardError, BoardInfo, HasGif, HasImage, HasScreen, HasScreenSize, HasSystemInfo,
-    HasTime, HasWeather, Result, ScreenGroup, ScreenPosition as CoreScreenPosition,
+    HasTime, HasWeather, Result, ScreenGroup, ScreenPosition as CoreScreenPosition, WeatherIcon,
 };
 
 pub mod abi;
@@ -227,6 +227,23 @@ impl Zoom65v3 {
             .ok_or(BoardError::CommandFailed("device rejected command"))
     }
 
+    /// Show raw values in the hour and minute fields of the clock, which the keyboard shows as
+    /// two digits each, on the current day.
+    pub fn set_clock_fields(&mut self, hour: u8, minute: u8) -> Result<()> {
+        let today = Local::now();
+        let res = self.execute(abi::set_time(
+            (today.year() % 100) as u8,
+            today.month() as u8,
+            today.day() as u8,
+            hour,
+            minute,
+            0,
+        ))?;
+        (res[1] == 1 && res[2] == 1)
+            .then_some(())
+            .ok_or(BoardError::CommandFailed("device rejected command"))
+    }
+
     /// Update the keyboards current weather report
     #[inline(always)]
     pub fn set_weather(&mut self, icon: Icon, current: u8, low: u8, high: u8) -> Result<()> {
@@ -395,16 +412,30 @@ impl Board for Zoom65v3 {
 }
 
 impl HasTime for Zoom65v3 {
-    fn set_time(&mut self, time: DateTime<Local>, use_12hr: bool) -> Result<()> {
+    fn set_time(&mut self, time: DateTime<FixedOffset>, use_12hr: bool) -> Result<()> {
         Zoom65v3::set_time(self, time, use_12hr)
     }
+
+    fn set_clock_fields(&mut self, hour: u8, minute: u8) -> Result<()> {
+        Zoom65v3::set_clock_fields(self, hour, minute)
+    }
 }
 
 impl HasWeather for Zoom65v3 {
     fn set_weather(&mut self, wmo: u8, is_day: bool, current: u8, low: u8, high: u8) -> Result<()> {
-        let icon =
-            Icon::from_wmo(wmo, is_day).ok_or(BoardError::CommandFailed("unknown WMO code"))?;
-        Zoom65v3::set_weather(self, icon, current, low, high)
+        let icon = WeatherIcon::from_wmo(wmo, is_day)
+            .ok_or(BoardError::CommandFailed("unknown WMO code"))?;
+        self.set_weather_icon(icon, current, low, high)
+    }
+
+    fn set_weather_icon(
+        &mut self,
+        icon: WeatherIcon,
+        current: u8,
+        low: u8,
+        high: u8,
+    ) -> Result<()> {
+        Zoom65v3::set_weather(self, icon.into(), current, low, high)
     }
 }
 
@@ -438,6 +469,10 @@ impl HasScreen for Zoom65v3 {
     fn reset_screen(&mut self) -> Result<()> {
         Zoom65v3::reset_screen(self)
     }
+
+    fn set_theme(&mut self, theme: &str) -> Result<()> {
+        Zoom65v3::screen_theme(self, theme.parse().map_err(BoardError::InvalidTheme)?)
+    }
 }
 
 impl HasScreenSize for Zoom65v3 {
@@ -1,5 +1,7 @@
 use std::str::FromStr;
 
+use zoom_sync_core::WeatherIcon;
+
 use crate::abi::Arg;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
@@ -9,6 +11,17 @@ pub enum ScreenTheme {
     Pink = 2,
 }
 
+impl FromStr for ScreenTheme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "blue" => Ok(Self::Blue),
+            "pink" => Ok(Self::Pink),
+            _ => Err(format!("{s}, must be one of: blue, pink")),
+        }
+    }
+}
+
 impl Arg for ScreenTheme {
     const SIZE: usize = 1;
     fn to_bytes(&self) -> Vec<u8> {
@@ -48,43 +61,23 @@ pub enum Icon {
 
 impl Icon {
     /// Convert a WMO index into a weather icon, adapting for day and night
-    /// Adapted from the list at the bottom of <https://open-meteo.com/en/docs>
     pub fn from_wmo(wmo: u8, is_day: bool) -> Option<Self> {
-        match wmo {
-            // clear and mainly clear
-            0 | 1 => Some(if is_day { Icon::DayClear } else { Icon::NightClear }),
-
-            // partly cloudy
-            2 => Some(if is_day { Icon::DayPartlyCloudy } else { Icon::NightPartlyCloudy }),
-
-            // overcast
-            3
-            // foggy
-            | 45 | 48 => Some(Icon::Cloudy),
-
-            // drizzle
-            51 | 53 | 55
-            // freezing drizzle
-            |56 | 57
-            // rain
-            | 61 | 63 | 65
-            // freezing rain
-            | 66 | 67 => Some(Icon::Rainy),
-
-            // rain showers
-            80..=82 => Some(if is_day { Icon::DayPartlyRainy } else { Icon::Rainy }),
-
-
-            // snowfall
-            | 71 | 73 | 75 | 77
-            // snow showers
-            | 85 | 86 => Some(Icon::Snowfall),
-
-            // thunderstorm
-            95 | 96 | 99 => Some(Icon::Thunderstorm),
+        WeatherIcon::from_wmo(wmo, is_day).map(Into::into)
+    }
+}
 
-            // unknown
-            _ => None
+impl From<WeatherIcon> for Icon {
+    fn from(icon: WeatherIcon) -> Self {
+        match icon {
+            WeatherIcon::DayClear => Icon::DayClear,
+            WeatherIcon::DayPartlyCloudy => Icon::DayPartlyCloudy,
+            WeatherIcon::DayPartlyRainy => Icon::DayPartlyRainy,
+            WeatherIcon::NightPartlyCloudy => Icon::NightPartlyCloudy,
+            WeatherIcon::NightClear => Icon::NightClear,
+            WeatherIcon::Cloudy => Icon::Cloudy,
+            WeatherIcon::Rainy => Icon::Rainy,
+            WeatherIcon::Snowfall => Icon::Snowfall,
+            WeatherIcon::Thunderstorm => Icon::Thunderstorm,
         }
     }
 }
@@ -0,0 +1,178 @@
+//! Severe weather alerts, evaluated against the fetched weather
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::weather::{WeatherData, WmoRange};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Enable weather alerts
+    pub enabled: bool,
+    /// Screen to switch to when an alert triggers
+    pub screen: String,
+    /// How long to show the alert screen before reverting, unless dismissed earlier
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    /// Alert rules, each triggering when all of its conditions match
+    pub rules: Vec<AlertRule>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        let rule = |name: &str| AlertRule {
+            name: name.into(),
+            ..Default::default()
+        };
+        Self {
+            enabled: false,
+            screen: "weather".into(),
+            timeout: Duration::from_secs(60),
+            rules: vec![
+                AlertRule {
+                    wmo: vec![WmoRange { start: 95, end: 99 }],
+                    ..rule("Thunderstorm")
+                },
+                AlertRule {
+                    wmo: vec![
+                        WmoRange { start: 75, end: 75 },
+                        WmoRange { start: 86, end: 86 },
+                    ],
+                    ..rule("Heavy snow")
+                },
+                AlertRule {
+                    precipitation: Some(20.),
+                    ..rule("Heavy rain")
+                },
+                AlertRule {
+                    wind_gusts: Some(20.),
+                    ..rule("Strong wind gusts")
+                },
+            ],
+        }
+    }
+}
+
+/// A named set of conditions on the weather. Unset conditions are ignored, and a rule without
+/// any conditions never triggers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertRule {
+    pub name: String,
+    /// Weather codes or ranges of codes, ie ["95-99"]
+    pub wmo: Vec<WmoRange>,
+    /// Minimum precipitation in mm over the forecast period
+    pub precipitation: Option<f32>,
+    /// Minimum wind gusts in m/s over the forecast period
+    pub wind_gusts: Option<f32>,
+}
+
+impl AlertRule {
+    /// Check if the weather matches all of the rule's conditions
+    pub fn matches(&self, data: &WeatherData) -> bool {
+        let conditions = [
+            (!self.wmo.is_empty()).then(|| self.wmo.iter().any(|r| r.contains(data.wmo))),
+            self.precipitation
+                .map(|min| data.precipitation.is_some_and(|p| p >= min)),
+            self.wind_gusts
+                .map(|min| data.wind_gusts.is_some_and(|g| g >= min)),
+        ];
+        let mut conditions = conditions.into_iter().flatten().peekable();
+        conditions.peek().is_some() && conditions.all(|c| c)
+    }
+}
+
+/// Tracks which alerts are active, so each one only notifies once while it lasts
+#[derive(Debug, Default)]
+pub struct AlertState {
+    active: BTreeSet<String>,
+}
+
+impl AlertState {
+    /// Evaluate the rules against the weather of each (optionally named) location, returning
+    /// the alerts that weren't already active
+    pub fn update<'a>(
+        &mut self,
+        rules: &[AlertRule],
+        weather: impl IntoIterator<Item = (Option<&'a str>, &'a WeatherData)>,
+    ) -> Vec<String> {
+        let triggered: BTreeSet<String> = weather
+            .into_iter()
+            .flat_map(|(location, data)| {
+                rules
+                    .iter()
+                    .filter(|rule| rule.matches(data))
+                    .map(move |rule| match location {
+                        Some(location) => format!("{} ({location})", rule.name),
+                        None => rule.name.clone(),
+                    })
+            })
+            .collect();
+        let new = triggered.difference(&self.active).cloned().collect();
+        self.active = triggered;
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather(wmo: u8, precipitation: f32, wind_gusts: f32) -> WeatherData {
+        WeatherData {
+            wmo,
+            precipitation: Some(precipitation),
+            wind_gusts: Some(wind_gusts),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let rules = AlertConfig::default().rules;
+        let names = |data: &WeatherData| -> Vec<_> {
+            rules
+                .iter()
+                .filter(|r| r.matches(data))
+                .map(|r| r.name.as_str())
+                .collect()
+        };
+        assert!(names(&weather(3, 0.4, 8.)).is_empty());
+        assert_eq!(names(&weather(96, 2., 8.)), ["Thunderstorm"]);
+        assert_eq!(
+            names(&weather(65, 25., 22.)),
+            ["Heavy rain", "Strong wind gusts"]
+        );
+        // unreported values never match
+        assert!(names(&WeatherData::default()).is_empty());
+
+        // all conditions of a rule must match
+        let rule = AlertRule {
+            name: "Blizzard".into(),
+            wmo: vec!["71-77".parse().unwrap()],
+            wind_gusts: Some(15.),
+            ..Default::default()
+        };
+        assert!(rule.matches(&weather(75, 0., 18.)));
+        assert!(!rule.matches(&weather(75, 0., 5.)));
+        assert!(!AlertRule::default().matches(&weather(95, 50., 50.)));
+    }
+
+    #[test]
+    fn notify_once() {
+        let rules = AlertConfig::default().rules;
+        let mut state = AlertState::default();
+        let storm = weather(95, 0., 0.);
+        let calm = weather(0, 0., 0.);
+        assert_eq!(state.update(&rules, [(None, &storm)]), ["Thunderstorm"]);
+        assert!(state.update(&rules, [(None, &storm)]).is_empty());
+        assert!(state.update(&rules, [(None, &calm)]).is_empty());
+        assert_eq!(
+            state.update(&rules, [(None, &storm), (Some("HQ"), &storm)]),
+            ["Thunderstorm", "Thunderstorm (HQ)"]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Automatic cycling through a list of screens
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Shortest time to show a screen, so a zero dwell can't spin
+const MIN_DWELL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CarouselConfig {
+    /// Cycle through the screens
+    pub enabled: bool,
+    /// How long to pause cycling after choosing a screen from the tray
+    #[serde(with = "humantime_serde")]
+    pub idle_timeout: Duration,
+    /// Screens to show in order, with how long to show each one
+    pub screens: Vec<CarouselEntry>,
+}
+
+impl Default for CarouselConfig {
+    fn default() -> Self {
+        let entry = |screen: &str, secs| CarouselEntry {
+            screen: screen.into(),
+            dwell: Duration::from_secs(secs),
+        };
+        Self {
+            enabled: false,
+            idle_timeout: Duration::from_secs(5 * 60),
+            screens: vec![entry("time", 30), entry("weather", 30), entry("cpu", 15)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarouselEntry {
+    /// Screen id, ie "weather"
+    pub screen: String,
+    /// How long to show the screen
+    #[serde(with = "humantime_serde")]
+    pub dwell: Duration,
+}
+
+/// Next action for the carousel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CarouselStep {
+    /// Show a screen, then step again after the delay
+    Show(String, Duration),
+    /// Step again after the delay without changing the screen
+    Wait(Duration),
+}
+
+/// Position in the carousel, and when to resume after a user change
+#[derive(Debug, Clone, Default)]
+pub struct Carousel {
+    position: Option<usize>,
+    resume_at: Option<Instant>,
+}
+
+impl Carousel {
+    /// Pause cycling until the idle timeout passes
+    pub fn pause(&mut self, now: Instant, idle_timeout: Duration) {
+        self.resume_at = Some(now + idle_timeout);
+    }
+
+    /// Advance to the next screen, unless paused or something else owns the screen
+    pub fn step(&mut self, config: &CarouselConfig, now: Instant, busy: bool) -> CarouselStep {
+        // check again soon, so cycling continues right after an alert or timer ends
+        if busy {
+            return CarouselStep::Wait(MIN_DWELL);
+        }
+        if let Some(resume_at) = self.resume_at {
+            if resume_at > now {
+                return CarouselStep::Wait(resume_at - now);
+            }
+            self.resume_at = None;
+        }
+        if config.screens.is_empty() {
+            return CarouselStep::Wait(config.idle_timeout.max(MIN_DWELL));
+        }
+        let next = self.position.map_or(0, |i| (i + 1) % config.screens.len());
+        self.position = Some(next);
+        let entry = &config.screens[next];
+        CarouselStep::Show(entry.screen.clone(), entry.dwell.max(MIN_DWELL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        let config: CarouselConfig = toml::from_str(
+            r#"
+            idle_timeout = "1m"
+            screens = [
+                { screen = "time", dwell = "30s" },
+                { screen = "weather", dwell = "0s" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let start = Instant::now();
+        let mut carousel = Carousel::default();
+        let show =
+            |screen: &str, secs| CarouselStep::Show(screen.into(), Duration::from_secs(secs));
+        assert_eq!(carousel.step(&config, start, false), show("time", 30));
+        assert_eq!(carousel.step(&config, start, false), show("weather", 1));
+
+        // a user change pauses until the idle timeout, then cycling continues where it was
+        carousel.pause(start, config.idle_timeout);
+        let later = start + Duration::from_secs(45);
+        assert_eq!(
+            carousel.step(&config, later, false),
+            CarouselStep::Wait(Duration::from_secs(15))
+        );
+        assert_eq!(
+            carousel.step(&config, later + Duration::from_secs(15), false),
+            show("time", 30)
+        );
+    }
+
+    #[test]
+    fn busy_polls_at_min_dwell() {
+        let config = CarouselConfig::default();
+        let mut carousel = Carousel::default();
+        assert_eq!(
+            carousel.step(&config, Instant::now(), true),
+            CarouselStep::Wait(MIN_DWELL)
+        );
+        // busy steps don't advance the position
+        assert_eq!(
+            carousel.step(&config, Instant::now(), false),
+            CarouselStep::Show("time".into(), Duration::from_secs(30))
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Keyboard clock scheduling, and detection of local clock changes that need a resync
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use chrono::{
+    DateTime, FixedOffset, Local, LocalResult, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    Timelike, Utc,
+};
+use chrono_tz::Tz;
+
+/// How often to check for utc offset changes and wall clock jumps
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wall clock drift from the monotonic clock between checks, before it counts as a jump
+const JUMP_THRESHOLD: TimeDelta = TimeDelta::seconds(2);
+
+/// Delay after a second boundary before syncing, so the time sent is just past it
+const SYNC_MARGIN: Duration = Duration::from_millis(20);
+
+/// Current time in a timezone, or in local time if not given
+pub fn now(tz: Option<Tz>) -> DateTime<FixedOffset> {
+    match tz {
+        Some(tz) => Utc::now().with_timezone(&tz).fixed_offset(),
+        None => Local::now().fixed_offset(),
+    }
+}
+
+/// Time to set instead of the current time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeAt {
+    /// Specific instant, ie 2024-05-01T13:15:00+02:00
+    Instant(DateTime<FixedOffset>),
+    /// Wall time today, ie 13:15 or 13:15:30
+    Clock(NaiveTime),
+}
+
+impl FromStr for TimeAt {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self::Instant(time));
+        }
+        NaiveTime::parse_from_str(s, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+            .map(Self::Clock)
+            .map_err(|_| format!("invalid time '{s}', must be RFC3339 or HH:MM[:SS]"))
+    }
+}
+
+/// Signed offset to add to a time, ie 5m or -1h30m
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeOffset(pub TimeDelta);
+
+impl FromStr for TimeOffset {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, duration) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let delta = humantime::parse_duration(duration)
+            .ok()
+            .and_then(|d| TimeDelta::from_std(d).ok())
+            .ok_or_else(|| format!("invalid offset '{s}', must be a duration like 5m or -1h"))?;
+        Ok(Self(if negative { -delta } else { delta }))
+    }
+}
+
+/// Resolve the time to set: the given time or the current one, in the given timezone or as is,
+/// plus the offset
+pub fn resolve(
+    at: Option<TimeAt>,
+    tz: Option<Tz>,
+    offset: Option<TimeOffset>,
+) -> Result<DateTime<FixedOffset>, String> {
+    let time = match at {
+        None => now(tz),
+        Some(TimeAt::Instant(time)) => match tz {
+            Some(tz) => time.with_timezone(&tz).fixed_offset(),
+            None => time,
+        },
+        Some(TimeAt::Clock(clock)) => {
+            let today = now(tz).date_naive().and_time(clock);
+            match tz {
+                Some(tz) => on_day(&tz, today)?,
+                None => on_day(&Local, today)?,
+            }
+        },
+    };
+    let offset = offset.map_or(TimeDelta::zero(), |o| o.0);
+    time.checked_add_signed(offset)
+        .ok_or_else(|| "time offset out of range".into())
+}
+
+/// Resolve a wall time in a timezone. Times skipped by a dst transition are rejected, and
+/// times repeated by one resolve to their first occurrence.
+fn on_day<Z: TimeZone>(tz: &Z, time: NaiveDateTime) -> Result<DateTime<FixedOffset>, String> {
+    match tz.from_local_datetime(&time) {
+        LocalResult::Single(time) => Ok(time.fixed_offset()),
+        LocalResult::Ambiguous(first, _) => Ok(first.fixed_offset()),
+        LocalResult::None => Err(format!(
+            "{} does not exist on {}, skipped by a dst transition",
+            time.time(),
+            time.date()
+        )),
+    }
+}
+
+/// Change in the shown clock since the last check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockChange {
+    /// Utc offset changed, ie a dst transition or a new system timezone
+    Offset { from: FixedOffset, to: FixedOffset },
+    /// Wall clock jumped relative to the monotonic clock, ie after a suspend or ntp step
+    Jump(TimeDelta),
+}
+
+impl Display for ClockChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClockChange::Offset { from, to } => write!(f, "utc offset changed from {from} to {to}"),
+            ClockChange::Jump(delta) => write!(f, "clock jumped by {}s", delta.num_seconds()),
+        }
+    }
+}
+
+/// Watches the clock shown on the keyboard for changes between checks
+#[derive(Clone, Copy, Debug)]
+pub struct ClockWatch {
+    instant: Instant,
+    wall: DateTime<Utc>,
+    offset: FixedOffset,
+}
+
+impl ClockWatch {
+    /// Start watching the clock in a timezone, or in local time if not given
+    pub fn new(tz: Option<Tz>) -> Self {
+        Self::at(Instant::now(), now(tz))
+    }
+
+    fn at(instant: Instant, now: DateTime<FixedOffset>) -> Self {
+        Self {
+            instant,
+            wall: now.to_utc(),
+            offset: *now.offset(),
+        }
+    }
+
+    /// Check for a change since the last check
+    pub fn check(&mut self, tz: Option<Tz>) -> Option<ClockChange> {
+        self.check_at(Instant::now(), now(tz))
+    }
+
+    fn check_at(&mut self, instant: Instant, now: DateTime<FixedOffset>) -> Option<ClockChange> {
+        let last = std::mem::replace(self, Self::at(instant, now));
+        let elapsed = TimeDelta::from_std(instant - last.instant).unwrap_or(TimeDelta::MAX);
+        let drift = (self.wall - last.wall) - elapsed;
+        if last.offset != self.offset {
+            Some(ClockChange::Offset {
+                from: last.offset,
+                to: self.offset,
+            })
+        } else if drift.abs() > JUMP_THRESHOLD {
+            Some(ClockChange::Jump(drift))
+        } else {
+            None
+        }
+    }
+}
+
+/// Time until just after the next second boundary
+pub fn until_next_second(now: &DateTime<FixedOffset>) -> Duration {
+    // leap seconds are reported as nanoseconds past one billion
+    let nanos = now.nanosecond() % 1_000_000_000;
+    Duration::from_nanos(1_000_000_000 - nanos as u64) + SYNC_MARGIN
+}
+
+/// Time until the next scheduled sync, aligned to a second boundary. 12-hour mode syncs on the
+/// hour so the simulated hour rolls over, otherwise the clock is resynced every interval.
+pub fn until_sync(now: &DateTime<FixedOffset>, use_12hr: bool, interval: Duration) -> Duration {
+    let seconds = if use_12hr {
+        3599 - (now.minute() * 60 + now.second().min(59)) as u64
+    } else {
+        interval.as_secs().max(1) - 1
+    };
+    Duration::from_secs(seconds) + until_next_second(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    #[test]
+    fn changes() {
+        let start = Instant::now();
+        let mut watch = ClockWatch::at(start, time("2024-03-31T01:59:55+01:00"));
+        let after = |secs| start + Duration::from_secs(secs);
+        assert_eq!(
+            watch.check_at(after(5), time("2024-03-31T02:00:00+01:00")),
+            None
+        );
+        // dst starts
+        assert_eq!(
+            watch.check_at(after(10), time("2024-03-31T03:00:05+02:00")),
+            Some(ClockChange::Offset {
+                from: FixedOffset::east_opt(3600).unwrap(),
+                to: FixedOffset::east_opt(7200).unwrap(),
+            })
+        );
+        // resumed after an hour of suspend, without the monotonic clock advancing
+        assert_eq!(
+            watch.check_at(after(15), time("2024-03-31T04:00:10+02:00")),
+            Some(ClockChange::Jump(TimeDelta::hours(1)))
+        );
+        // small ntp slews are ignored
+        assert_eq!(
+            watch.check_at(after(20), time("2024-03-31T04:00:16+02:00")),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_time() {
+        let at = |s: &str| Some(s.parse::<TimeAt>().unwrap());
+        let offset = |s: &str| Some(s.parse::<TimeOffset>().unwrap());
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+
+        let berlin = resolve(at("2024-05-01T13:15:00+02:00"), None, None).unwrap();
+        assert_eq!(berlin, time("2024-05-01T13:15:00+02:00"));
+        let time = resolve(
+            at("2024-05-01T13:15:00+02:00"),
+            Some(tokyo),
+            offset("-1h30m"),
+        );
+        assert_eq!(time.unwrap().to_rfc3339(), "2024-05-01T18:45:00+09:00");
+
+        let time = resolve(at("07:30"), Some(tokyo), offset("+90s")).unwrap();
+        assert_eq!((time.hour(), time.minute(), time.second()), (7, 31, 30));
+        assert_eq!(time.offset().local_minus_utc(), 9 * 3600);
+
+        assert!("25:00".parse::<TimeAt>().is_err());
+        assert!("-soon".parse::<TimeOffset>().is_err());
+    }
+
+    #[test]
+    fn dst_transitions() {
+        let oslo: Tz = "Europe/Oslo".parse().unwrap();
+        let day = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        // skipped when dst starts
+        assert!(on_day(&oslo, day("2024-03-31 02:30")).is_err());
+        assert_eq!(
+            on_day(&oslo, day("2024-03-31 03:30")).unwrap(),
+            time("2024-03-31T03:30:00+02:00")
+        );
+        // repeated when dst ends, the first occurrence is still in summer time
+        assert_eq!(
+            on_day(&oslo, day("2024-10-27 02:30")).unwrap(),
+            time("2024-10-27T02:30:00+02:00")
+        );
+        assert_eq!(
+            on_day(&oslo, day("2024-10-27 03:30")).unwrap(),
+            time("2024-10-27T03:30:00+01:00")
+        );
+    }
+
+    #[test]
+    fn schedule() {
+        let now = time("2024-05-01T13:15:42.250+02:00");
+        let margin = SYNC_MARGIN.as_millis();
+        assert_eq!(until_next_second(&now).as_millis(), 750 + margin);
+        assert_eq!(
+            until_sync(&now, false, Duration::from_secs(600)).as_millis(),
+            599_750 + margin
+        );
+        // on the next hour
+        assert_eq!(
+            until_sync(&now, true, Duration::from_secs(600)).as_millis(),
+            ((44 * 60 + 17) * 1000 + 750) + margin
+        );
+    }
+}
@@ -1,12 +1,29 @@
 //! Configuration file handling for tray mode
 
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use chrono_tz::Tz;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use zoom_sync_core::WeatherIcon;
+
+use crate::alerts::AlertConfig;
+use crate::carousel::CarouselConfig;
+use crate::info::GpuDevice;
+use crate::network::{RateDirection, RateUnit};
+use crate::rules::Rule;
+use crate::schedule::ScheduleEntry;
+use crate::scripts::ScriptsConfig;
+use crate::sources::{Field, SourceConfig};
+use crate::temperature::RangeConfig;
+use crate::weather::{
+    ForecastMode, IconChoice, IconName, IpVersion, Provider, UnknownWeather, WmoRange,
+    EVENING_HOUR, GEOCODING_URL, GEOLOCATION_URL, IP_CHECK_URL, REQUEST_TIMEOUT,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -17,6 +34,21 @@ pub struct Config {
     pub weather: WeatherConfig,
     pub system_info: SystemInfoConfig,
     pub media: MediaConfig,
+    /// Severe weather alerts
+    pub alerts: AlertConfig,
+    /// Timezones to show on the keyboard clock in turn
+    pub world_clock: WorldClockConfig,
+    /// Screens to cycle through
+    pub carousel: CarouselConfig,
+    /// Screen, theme and feature toggles by time of day, where earlier entries take priority
+    pub schedule: Vec<ScheduleEntry>,
+    /// Actions to take while system conditions hold, ie a screen while a game runs
+    pub rules: Vec<Rule>,
+    /// User scripts in the scripts directory next to this file
+    pub scripts: ScriptsConfig,
+    /// Custom sources for display fields, keyed by field
+    /// ("cpu", "gpu", "download", "weather_current", "weather_min", "weather_max")
+    pub sources: BTreeMap<Field, SourceConfig>,
 }
 
 impl Config {
@@ -25,6 +57,19 @@ impl Config {
         ProjectDirs::from("", "", "zoom-sync").map(|dirs| dirs.config_dir().join("config.toml"))
     }
 
+    /// Get the user scripts directory for this platform
+    pub fn scripts_dir() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "zoom-sync").map(|dirs| dirs.config_dir().join("scripts"))
+    }
+
+    /// Load the config file if there is one, so the cli shows values like the tray does
+    pub fn load() -> Option<Self> {
+        let contents = fs::read_to_string(Self::path()?).ok()?;
+        toml::from_str(&contents)
+            .inspect_err(|e| eprintln!("warning: failed to parse config, using defaults: {e}"))
+            .ok()
+    }
+
     /// Load config from file, or create default if it doesn't exist
     pub fn load_or_create() -> Result<Self, Box<dyn Error>> {
         let path = Self::path().ok_or("could not determine config directory")?;
@@ -89,6 +134,14 @@ pub struct GeneralConfig {
     pub use_12hr_time: bool,
     /// Initial screen position on connect (use "reactive" for reactive mode on Linux)
     pub initial_screen: String,
+    /// Screen to show while the computer sleeps, ie "meletrix" (optional)
+    pub sleep_screen: Option<String>,
+    /// Timezone for the keyboard clock, ie "America/New_York" (optional, defaults to local time)
+    pub timezone: Option<Tz>,
+    /// How to display temperatures outside of 0-99 for each field ("cpu", "gpu",
+    /// "weather_current", "weather_min", "weather_max"). Either "clamp", "abs" (marking negative
+    /// weather with `negative_icon`), "switch" (show in the other unit), or { offset = N }.
+    pub ranges: RangeConfig,
 }
 
 impl Default for GeneralConfig {
@@ -97,6 +150,49 @@ impl Default for GeneralConfig {
             fahrenheit: false,
             use_12hr_time: false,
             initial_screen: "meletrix".into(),
+            sleep_screen: None,
+            timezone: None,
+            ranges: RangeConfig::default(),
+        }
+    }
+}
+
+/// Feature toggles set by the schedule or rules, leaving unset ones as they are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureToggles {
+    /// Enable or disable weather updates
+    pub weather: Option<bool>,
+    /// Enable or disable system info updates
+    pub system_info: Option<bool>,
+    /// Enable or disable the screen carousel
+    pub carousel: Option<bool>,
+}
+
+impl FeatureToggles {
+    /// Set the toggles given in another, keeping the rest
+    pub fn merge(&mut self, other: &FeatureToggles) {
+        self.weather = other.weather.or(self.weather);
+        self.system_info = other.system_info.or(self.system_info);
+        self.carousel = other.carousel.or(self.carousel);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldClockConfig {
+    /// Timezones to rotate between, ie ["Europe/Berlin", "America/New_York"], replacing the
+    /// general timezone (optional)
+    pub timezones: Vec<Tz>,
+    /// How long to show each timezone before rotating to the next one
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+}
+
+impl Default for WorldClockConfig {
+    fn default() -> Self {
+        Self {
+            timezones: Vec::new(),
+            interval: Duration::from_secs(10),
         }
     }
 }
@@ -113,6 +209,12 @@ pub struct RefreshConfig {
     /// Keyboard reconnection retry interval
     #[serde(with = "humantime_serde")]
     pub retry: Duration,
+    /// Clock resync interval in 24-hour mode (12-hour mode resyncs on the hour)
+    #[serde(with = "humantime_serde")]
+    pub time: Duration,
+    /// Rule evaluation interval
+    #[serde(with = "humantime_serde")]
+    pub rules: Duration,
 }
 
 impl Default for RefreshConfig {
@@ -121,6 +223,8 @@ impl Default for RefreshConfig {
             system: Duration::from_secs(10),
             weather: Duration::from_secs(60 * 60),
             retry: Duration::from_secs(5),
+            time: Duration::from_secs(60 * 60),
+            rules: Duration::from_secs(5),
         }
     }
 }
@@ -134,6 +238,57 @@ pub struct WeatherConfig {
     pub latitude: Option<f64>,
     /// Manual longitude (optional)
     pub longitude: Option<f64>,
+    /// City to fetch weather for, ie "Berlin, DE" (optional, used when no coordinates are set)
+    pub location: Option<String>,
+    /// Weather provider ("open-meteo", "met.no", "openweathermap", or "wttr.in")
+    pub provider: Provider,
+    /// Secondary weather provider, used when the primary one fails (optional)
+    pub fallback_provider: Option<Provider>,
+    /// API key for providers that require one (openweathermap)
+    pub api_key: Option<String>,
+    /// Base url for the primary weather provider, ie a self-hosted open-meteo instance (optional)
+    pub base_url: Option<String>,
+    /// Url for the geolocation api, either ipinfo-compatible or returning "latitude" and
+    /// "longitude" fields
+    pub geolocation_url: String,
+    /// Fallback geolocation apis, tried in order when the primary one fails
+    pub geolocation_fallbacks: Vec<String>,
+    /// Token for the ipinfo.io geolocation api (optional)
+    pub ipinfo_token: Option<String>,
+    /// Ip version to geolocate ("any", "v4", or "v6")
+    pub ip_version: IpVersion,
+    /// Url returning the public ip as plain text, checked before reusing the cached geolocation
+    pub ip_check_url: String,
+    /// Url for the open-meteo compatible geocoding api, used to find the location
+    pub geocoding_url: String,
+    /// Proxy for weather and geolocation requests, ie "http://proxy.local:3128" (optional)
+    pub proxy: Option<String>,
+    /// Timeout for weather and geolocation requests
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    /// Maximum age of cached weather to show when fetching fails, before showing the unknown
+    /// weather instead
+    #[serde(with = "humantime_serde")]
+    pub max_age: Duration,
+    /// Weather shown when there is no recent enough data (wmo code, current, min, max)
+    pub unknown: UnknownWeather,
+    /// Weather to show: "current", { hours = 6 } for the next hours, or "tomorrow" for
+    /// tomorrow's forecast after the evening hour
+    pub forecast: ForecastMode,
+    /// Hour of the day (0-23) at the weather location to switch to tomorrow's forecast
+    pub evening_hour: u8,
+    /// Show the apparent ("feels like") temperature instead of the air temperature
+    pub apparent: bool,
+    /// Icon shown for weather codes without an icon
+    pub fallback_icon: IconName,
+    /// Named locations to rotate between, replacing the single location above (optional)
+    pub locations: Vec<WeatherLocation>,
+    /// How long to show each location before rotating to the next one
+    #[serde(with = "humantime_serde")]
+    pub rotate_interval: Duration,
+    /// Icon overrides for wmo codes or ranges of codes, ie "45-48" = "day-clear", or
+    /// "80-82" = { day = "day-partly-rainy", night = "rainy" }
+    pub icons: BTreeMap<WmoRange, IconChoice>,
 }
 
 impl Default for WeatherConfig {
@@ -142,19 +297,65 @@ impl Default for WeatherConfig {
             enabled: true,
             latitude: None,
             longitude: None,
+            location: None,
+            provider: Provider::default(),
+            fallback_provider: None,
+            api_key: None,
+            base_url: None,
+            geolocation_url: GEOLOCATION_URL.into(),
+            geolocation_fallbacks: Vec::new(),
+            ipinfo_token: None,
+            ip_version: IpVersion::Any,
+            ip_check_url: IP_CHECK_URL.into(),
+            geocoding_url: GEOCODING_URL.into(),
+            proxy: None,
+            timeout: REQUEST_TIMEOUT,
+            max_age: Duration::from_secs(3 * 60 * 60),
+            unknown: UnknownWeather::default(),
+            forecast: ForecastMode::Current,
+            evening_hour: EVENING_HOUR,
+            apparent: false,
+            fallback_icon: IconName(WeatherIcon::Cloudy),
+            locations: Vec::new(),
+            rotate_interval: Duration::from_secs(15),
+            icons: BTreeMap::new(),
         }
     }
 }
 
+/// Named weather location, by coordinates or city name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherLocation {
+    /// Name shown in the tray tooltip, ie "Home"
+    pub name: String,
+    /// Latitude (optional)
+    pub latitude: Option<f64>,
+    /// Longitude (optional)
+    pub longitude: Option<f64>,
+    /// City, ie "Berlin, DE" (optional, used when no coordinates are set)
+    pub city: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct SystemInfoConfig {
     /// Enable system info updates
     pub enabled: bool,
-    /// CPU temperature sensor label ("auto" for automatic)
+    /// CPU temperature sensor label ("auto" for automatic), or an exact sensor spec listed by
+    /// `zoom-sync sensors` (ie "hwmon:k10temp:Tctl", "avg:hwmon:coretemp:Core*", "thermal:acpitz")
     pub cpu_source: String,
-    /// GPU device index
-    pub gpu_device: u32,
+    /// GPU device: nvidia device index, or a hwmon driver with an optional sensor
+    /// ("hwmon", "amdgpu", "radeon", "i915", "xe", ie "amdgpu:junction")
+    pub gpu_device: GpuDevice,
+    /// Network interface to monitor (optional, defaults to the interface of the default route)
+    pub network_interface: Option<String>,
+    /// Network traffic to report in the download field ("download" or "upload")
+    pub network_direction: RateDirection,
+    /// Network rate unit ("kb/s", "mb/s", or "mbit/s")
+    pub network_unit: RateUnit,
+    /// Scale the network rate up by 1000 (ie kb/s to mb/s) when too large to display, instead of
+    /// clamping. The unit shown is printed whenever it changes.
+    pub network_auto_scale: bool,
 }
 
 impl Default for SystemInfoConfig {
@@ -162,7 +363,11 @@ impl Default for SystemInfoConfig {
         Self {
             enabled: true,
             cpu_source: "Package".into(),
-            gpu_device: 0,
+            gpu_device: GpuDevice::default(),
+            network_interface: None,
+            network_direction: RateDirection::Download,
+            network_unit: RateUnit::KiloBytes,
+            network_auto_scale: true,
         }
     }
 }
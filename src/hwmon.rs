@@ -0,0 +1,142 @@
+//! Utilities for reading temperature sensors from the linux hwmon sysfs interface
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default hwmon sysfs root
+pub const HWMON_ROOT: &str = "/sys/class/hwmon";
+
+/// A hwmon chip, ie `/sys/class/hwmon/hwmon3`
+#[derive(Clone, Debug)]
+pub struct Chip {
+    pub path: PathBuf,
+    /// Driver name, read from the `name` file
+    pub name: String,
+}
+
+/// A temperature input on a hwmon chip, ie `temp1_input`
+#[derive(Clone, Debug)]
+pub struct TempInput {
+    pub path: PathBuf,
+    /// Optional label, read from the matching `tempN_label` file
+    pub label: Option<String>,
+}
+
+/// List all hwmon chips under a root directory, sorted by their path
+pub fn chips(root: &Path) -> Vec<Chip> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut chips: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = fs::read_to_string(path.join("name")).ok()?;
+            Some(Chip {
+                name: name.trim().to_string(),
+                path,
+            })
+        })
+        .collect();
+    chips.sort_by_key(|c| sort_key(&c.path));
+    chips
+}
+
+impl Chip {
+    /// List all temperature inputs on this chip, sorted by index
+    pub fn temps(&self) -> Vec<TempInput> {
+        let Ok(entries) = fs::read_dir(&self.path) else {
+            return Vec::new();
+        };
+        let mut temps: Vec<_> = entries
+            .filter_map(|entry| {
+                let file_name = entry.ok()?.file_name().into_string().ok()?;
+                let prefix = file_name
+                    .strip_prefix("temp")?
+                    .strip_suffix("_input")?
+                    .to_string();
+                let label = fs::read_to_string(self.path.join(format!("temp{prefix}_label")))
+                    .ok()
+                    .map(|l| l.trim().to_string());
+                Some(TempInput {
+                    path: self.path.join(file_name),
+                    label,
+                })
+            })
+            .collect();
+        temps.sort_by_key(|t| sort_key(&t.path));
+        temps
+    }
+}
+
+/// Read a sysfs temperature file, which is reported in millidegrees celsius
+pub fn read_temp(path: &Path) -> Option<f32> {
+    let raw = fs::read_to_string(path).ok()?;
+    let millis: i64 = raw.trim().parse().ok()?;
+    Some(millis as f32 / 1000.)
+}
+
+/// Sort key for numbered sysfs entries, so that `hwmon10` comes after `hwmon9`
+fn sort_key(path: &Path) -> (String, u32) {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let prefix: String = name.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let index = name[prefix.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or_default();
+    (prefix, index)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Chip name and a list of `(label, millidegrees)` inputs
+    pub type FakeChip<'a> = (&'a str, &'a [(Option<&'a str>, i64)]);
+
+    /// Build a fake hwmon tree in a temporary directory
+    pub fn fake_hwmon(test: &str, chips: &[FakeChip]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("zoom-sync-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (i, (name, temps)) in chips.iter().enumerate() {
+            let chip = root.join(format!("hwmon{i}"));
+            fs::create_dir_all(&chip).unwrap();
+            fs::write(chip.join("name"), format!("{name}\n")).unwrap();
+            for (j, (label, value)) in temps.iter().enumerate() {
+                let n = j + 1;
+                fs::write(chip.join(format!("temp{n}_input")), format!("{value}\n")).unwrap();
+                if let Some(label) = label {
+                    fs::write(chip.join(format!("temp{n}_label")), format!("{label}\n")).unwrap();
+                }
+            }
+        }
+        root
+    }
+
+    #[test]
+    fn discover() {
+        let root = fake_hwmon(
+            "hwmon-discover",
+            &[
+                ("nvme", &[(Some("Composite"), 38850)]),
+                (
+                    "amdgpu",
+                    &[(Some("edge"), 45000), (Some("junction"), 51000)],
+                ),
+            ],
+        );
+        let found = chips(&root);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].name, "amdgpu");
+
+        let temps = found[1].temps();
+        assert_eq!(temps[1].label.as_deref(), Some("junction"));
+        assert_eq!(read_temp(&temps[1].path), Some(51.));
+        assert!(chips(Path::new("/nonexistent")).is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
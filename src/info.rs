@@ -1,19 +1,30 @@
 //! Utilities for getting system info
 
 use std::error::Error;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::LazyLock;
 
 use either::Either;
 use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
 use nvml_wrapper::{Device, Nvml};
+use serde::{Deserialize, Serialize};
 use sysinfo::{Component, Components};
 use zoom_sync_core::Board;
 
+use crate::hwmon::{self, HWMON_ROOT};
+use crate::network::{NetworkRate, RateDirection, RateUnit};
+use crate::sensors::{SensorSpec, TempSensor};
+use crate::temperature::RangeConfig;
+
 #[derive(Clone, Debug, bpaf::Bpaf)]
 pub enum CpuMode {
     Label(
-        /// Sensor label to search for
-        #[bpaf(long("cpu"), argument("LABEL"), fallback("Package".into()), display_fallback)]
+        /// Sensor label to search for, or an exact sensor from `zoom-sync sensors`
+        /// [LABEL|hwmon:CHIP[:LABEL]|thermal:TYPE|file:PATH], optionally prefixed with max: or
+        /// avg: to aggregate multiple matching sensors
+        #[bpaf(long("cpu"), argument("SENSOR"), fallback("Package".into()), display_fallback)]
         String,
     ),
     Manual(
@@ -24,20 +35,27 @@ pub enum CpuMode {
 }
 
 impl CpuMode {
-    pub fn either(&self) -> Either<CpuTemp, u8> {
+    pub fn either(&self) -> Either<CpuTemp, f32> {
         match self {
             CpuMode::Label(label) => Either::Left(CpuTemp::new(label)),
-            CpuMode::Manual(v) => Either::Right(*v),
+            CpuMode::Manual(v) => Either::Right(*v as f32),
         }
     }
 }
 
 #[derive(Clone, Debug, bpaf::Bpaf)]
 pub enum GpuMode {
-    Id(
-        /// GPU device id to fetch temperature data for (nvidia only)
-        #[bpaf(long("gpu"), argument::<u32>("ID"), fallback(0), display_fallback)]
-        u32,
+    Device(
+        /// GPU to fetch temperature data for. Either an nvidia device id (falling back to hwmon
+        /// when nvml is unavailable), or a linux hwmon driver with an optional sensor.
+        /// [ID|hwmon|amdgpu|radeon|i915|xe][:edge|junction|mem]
+        #[bpaf(
+            long("gpu"),
+            argument("DEVICE"),
+            fallback(GpuDevice::Index(0)),
+            display_fallback
+        )]
+        GpuDevice,
     ),
     Manual(
         /// Manually set GPU temperature
@@ -47,22 +65,226 @@ pub enum GpuMode {
 }
 
 impl GpuMode {
-    pub fn either(&self) -> Either<GpuTemp, u8> {
+    pub fn either(&self) -> Either<GpuTemp, f32> {
+        match self {
+            GpuMode::Device(d) => Either::Left(GpuTemp::new(d)),
+            GpuMode::Manual(v) => Either::Right(*v as f32),
+        }
+    }
+}
+
+/// Hwmon drivers for supported amd and intel gpus
+const GPU_DRIVERS: &[&str] = &["amdgpu", "radeon", "i915", "xe"];
+
+/// GPU temperature sensor to read from hwmon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GpuSensor {
+    #[default]
+    Edge,
+    Junction,
+    Mem,
+}
+
+impl GpuSensor {
+    /// Sensor labels reported by the supported drivers, in order of preference
+    fn labels(&self) -> &'static [&'static str] {
+        match self {
+            GpuSensor::Edge => &["edge"],
+            GpuSensor::Junction => &["junction", "pkg"],
+            GpuSensor::Mem => &["mem", "vram"],
+        }
+    }
+}
+
+impl FromStr for GpuSensor {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "edge" => Ok(Self::Edge),
+            "junction" | "hotspot" => Ok(Self::Junction),
+            "mem" | "memory" | "vram" => Ok(Self::Mem),
+            _ => Err(format!(
+                "invalid gpu sensor '{s}', must be one of: edge, junction, mem"
+            )),
+        }
+    }
+}
+
+impl Display for GpuSensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GpuSensor::Edge => "edge",
+            GpuSensor::Junction => "junction",
+            GpuSensor::Mem => "mem",
+        })
+    }
+}
+
+/// GPU selection for temperature monitoring
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "GpuDeviceRepr", into = "GpuDeviceRepr")]
+pub enum GpuDevice {
+    /// Nvidia device index, falling back to the first hwmon gpu if nvml is unavailable
+    Index(u32),
+    /// Hwmon gpu, optionally filtered by driver name. Without a sensor, the edge sensor or the
+    /// first input is used.
+    Hwmon {
+        driver: Option<String>,
+        sensor: Option<GpuSensor>,
+    },
+}
+
+impl Default for GpuDevice {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
+
+impl FromStr for GpuDevice {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(index) = s.parse() {
+            return Ok(Self::Index(index));
+        }
+        let (driver, sensor) = match s.split_once(':') {
+            Some((driver, sensor)) => (driver, Some(sensor.parse()?)),
+            None => (s, None),
+        };
+        let driver = driver.to_lowercase();
+        match driver.as_str() {
+            "hwmon" => Ok(Self::Hwmon {
+                driver: None,
+                sensor,
+            }),
+            d if GPU_DRIVERS.contains(&d) => Ok(Self::Hwmon {
+                driver: Some(driver),
+                sensor,
+            }),
+            _ => Err(format!(
+                "invalid gpu '{s}', must be a device id or one of: hwmon, {}",
+                GPU_DRIVERS.join(", ")
+            )),
+        }
+    }
+}
+
+impl Display for GpuDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuDevice::Index(i) => write!(f, "{i}"),
+            GpuDevice::Hwmon { driver, sensor } => {
+                f.write_str(driver.as_deref().unwrap_or("hwmon"))?;
+                match sensor {
+                    Some(sensor) => write!(f, ":{sensor}"),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+/// Config representation, keeping plain device indexes as integers
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum GpuDeviceRepr {
+    Index(u32),
+    Name(String),
+}
+
+impl TryFrom<GpuDeviceRepr> for GpuDevice {
+    type Error = String;
+    fn try_from(value: GpuDeviceRepr) -> Result<Self, Self::Error> {
+        match value {
+            GpuDeviceRepr::Index(i) => Ok(Self::Index(i)),
+            GpuDeviceRepr::Name(s) => s.parse(),
+        }
+    }
+}
+
+impl From<GpuDevice> for GpuDeviceRepr {
+    fn from(value: GpuDevice) -> Self {
+        match value {
+            GpuDevice::Index(i) => Self::Index(i),
+            d => Self::Name(d.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, bpaf::Bpaf)]
+pub enum DownloadMode {
+    Monitor {
+        /// Monitor the network rate instead of setting the download speed manually
+        #[bpaf(long("monitor-net"))]
+        monitor: (),
+        /// Network interface to monitor (defaults to the interface of the default route)
+        #[bpaf(long("net"), argument("IFACE"))]
+        interface: Option<String>,
+        /// Monitor the upload rate instead of the download rate
+        #[bpaf(long("upload"))]
+        upload: bool,
+        /// Unit for the network rate. [kb/s|mb/s|mbit/s]
+        #[bpaf(
+            long("net-unit"),
+            argument("UNIT"),
+            fallback(RateUnit::KiloBytes),
+            display_fallback
+        )]
+        unit: RateUnit,
+        /// Clamp the rate when too large to display, instead of scaling it up by 1000 (ie kb/s
+        /// to mb/s) and printing the unit shown whenever it changes
+        #[bpaf(long("no-net-auto-scale"))]
+        no_auto_scale: bool,
+    },
+    Manual(
+        /// Manually set download speed
+        #[bpaf(short('d'), long("download"), argument("RATE"), fallback(0.))]
+        f32,
+    ),
+}
+
+impl DownloadMode {
+    pub fn either(&self) -> Either<NetworkRate, f32> {
         match self {
-            GpuMode::Id(i) => Either::Left(GpuTemp::new(*i)),
-            GpuMode::Manual(v) => Either::Right(*v),
+            DownloadMode::Monitor {
+                monitor: (),
+                interface,
+                upload,
+                unit,
+                no_auto_scale,
+            } => Either::Left(NetworkRate::new(
+                interface.as_deref(),
+                if *upload {
+                    RateDirection::Upload
+                } else {
+                    RateDirection::Download
+                },
+                *unit,
+                !*no_auto_scale,
+            )),
+            DownloadMode::Manual(v) => Either::Right(*v),
         }
     }
 }
 
+/// Source for gpu temperature readings
+enum GpuSource {
+    Nvml(Device<'static>),
+    Hwmon(PathBuf),
+}
+
 /// Helper struct to track gpu temperature
 pub struct GpuTemp {
-    maybe_device: Option<Device<'static>>,
+    maybe_source: Option<GpuSource>,
 }
 
 impl GpuTemp {
-    /// Construct a new gpu temperature monitor, optionally selecting by device index
-    pub fn new(index: u32) -> Self {
+    /// Construct a new gpu temperature monitor for the selected device
+    pub fn new(device: &GpuDevice) -> Self {
+        Self::with_hwmon_root(device, Path::new(HWMON_ROOT))
+    }
+
+    /// Construct a new gpu temperature monitor, discovering hwmon gpus under a specific root
+    pub fn with_hwmon_root(device: &GpuDevice, root: &Path) -> Self {
         static NVML: LazyLock<Option<Nvml>> = LazyLock::new(|| {
             let nvml = Nvml::init().ok();
             if nvml.is_none() {
@@ -71,97 +293,151 @@ impl GpuTemp {
             nvml
         });
 
-        let maybe_device = NVML.as_ref().and_then(|nvml| {
-            let device = nvml.device_by_index(index).ok();
-            if device.is_none() {
-                eprintln!("warning: gpu device {index} not found")
-            }
-            device
-        });
+        let maybe_source = match device {
+            GpuDevice::Index(index) => match NVML.as_ref() {
+                Some(nvml) => {
+                    let device = nvml.device_by_index(*index).ok();
+                    if device.is_none() {
+                        eprintln!("warning: gpu device {index} not found")
+                    }
+                    device.map(GpuSource::Nvml)
+                },
+                // Fall back to any amd or intel gpu
+                None => find_hwmon_gpu(root, None, None).map(GpuSource::Hwmon),
+            },
+            GpuDevice::Hwmon { driver, sensor } => {
+                let source = find_hwmon_gpu(root, driver.as_deref(), *sensor);
+                if source.is_none() {
+                    eprintln!("warning: hwmon gpu {device} not found");
+                }
+                source.map(GpuSource::Hwmon)
+            },
+        };
 
-        Self { maybe_device }
+        Self { maybe_source }
     }
 
     // Refresh and poll the current temperature
-    pub fn get_temp(&self, farenheit: bool) -> Option<u8> {
-        self.maybe_device
-            .as_ref()
-            .and_then(|d| d.temperature(TemperatureSensor::Gpu).ok())
-            .map(|v| {
-                if farenheit {
-                    (v as f64 * 9. / 5. + 32.) as u8
-                } else {
-                    v as u8
-                }
-            })
+    pub fn get_temp(&self, farenheit: bool) -> Option<f32> {
+        let temp = match self.maybe_source.as_ref()? {
+            GpuSource::Nvml(d) => d.temperature(TemperatureSensor::Gpu).ok()? as f32,
+            GpuSource::Hwmon(path) => hwmon::read_temp(path)?,
+        };
+        Some(if farenheit {
+            temp * 9. / 5. + 32.
+        } else {
+            temp
+        })
     }
 }
 
+/// Find the temperature input for the first hwmon gpu matching the driver and sensor. Without a
+/// sensor, the edge sensor is preferred and the first input used if the chip has none.
+fn find_hwmon_gpu(root: &Path, driver: Option<&str>, sensor: Option<GpuSensor>) -> Option<PathBuf> {
+    let chip = hwmon::chips(root).into_iter().find(|c| match driver {
+        Some(d) => c.name == d,
+        None => GPU_DRIVERS.contains(&c.name.as_str()),
+    })?;
+    let temps = chip.temps();
+    let labeled = sensor
+        .unwrap_or_default()
+        .labels()
+        .iter()
+        .find_map(|l| temps.iter().find(|t| t.label.as_deref() == Some(l)));
+    let input = match (labeled, sensor) {
+        (Some(input), _) => input,
+        (None, None) => temps.first()?,
+        // don't show a different sensor than the one asked for
+        (None, Some(sensor)) => {
+            eprintln!("warning: {} has no {sensor} temperature input", chip.name);
+            if !temps.is_empty() {
+                eprintln!("  available inputs:");
+                for t in &temps {
+                    let name = t.path.file_name().unwrap_or_default().to_string_lossy();
+                    match &t.label {
+                        Some(label) => eprintln!("    - {name} ({label})"),
+                        None => eprintln!("    - {name}"),
+                    }
+                }
+            }
+            return None;
+        },
+    };
+    println!(
+        "using {} gpu temperature from {}",
+        chip.name,
+        input.path.display()
+    );
+    Some(input.path.clone())
+}
+
+/// Source for cpu temperature readings
+enum CpuSource {
+    Component(Component),
+    Sensor(TempSensor),
+}
+
 pub struct CpuTemp {
-    maybe_cpu: Option<Component>,
+    maybe_cpu: Option<CpuSource>,
 }
 
 impl CpuTemp {
-    // Create a new cpu temp monitor, optionally selecting the component by a label search string
-    pub fn new(search_label: &str) -> Self {
-        let comps: Vec<_> = Components::new_with_refreshed_list().into();
+    // Create a new cpu temp monitor, either from a sensor spec (see `zoom-sync sensors`), or by
+    // selecting the component by a label search string
+    pub fn new(source: &str) -> Self {
+        if let Ok(spec) = source.parse::<SensorSpec>() {
+            return Self {
+                maybe_cpu: Some(CpuSource::Sensor(TempSensor::new(&spec))),
+            };
+        }
+
+        let mut comps: Vec<_> = Components::new_with_refreshed_list().into();
 
         // Try to find the specified sensor, or fall back to common alternatives
         let fallbacks = ["Tctl", "Package", "CPU"];
-        let mut matched_fallback = None;
-
-        let maybe_cpu = comps
-            .into_iter()
-            .find(|v| {
-                if v.label().contains(search_label) {
-                    return true;
-                }
-                // Check fallbacks while iterating
-                if matched_fallback.is_none() {
-                    for (i, fb) in fallbacks.iter().enumerate() {
-                        if v.label().contains(fb) {
-                            matched_fallback = Some(i);
-                            break;
-                        }
-                    }
-                }
-                false
-            })
+        let position = comps
+            .iter()
+            .position(|v| v.label().contains(source))
             .or_else(|| {
-                // Didn't find exact match, try fallbacks
-                if let Some(fb_idx) = matched_fallback {
-                    let comps: Vec<_> = Components::new_with_refreshed_list().into();
-                    let fb = fallbacks[fb_idx];
-                    return comps.into_iter().find(|v| v.label().contains(fb));
-                }
-                None
+                fallbacks
+                    .iter()
+                    .find_map(|fb| comps.iter().position(|v| v.label().contains(fb)))
             });
 
-        if maybe_cpu.is_none() {
-            let comps: Vec<_> = Components::new_with_refreshed_list().into();
-            eprintln!("warning: no cpu temp sensor found");
-            if !comps.is_empty() {
-                eprintln!("  available sensors:");
-                for c in &comps {
-                    eprintln!("    - {}", c.label());
+        let maybe_cpu = match position {
+            Some(i) => Some(CpuSource::Component(comps.swap_remove(i))),
+            None => {
+                eprintln!("warning: no cpu temp sensor found");
+                if !comps.is_empty() {
+                    eprintln!("  available sensors:");
+                    for c in &comps {
+                        eprintln!("    - {}", c.label());
+                    }
                 }
-            }
-        }
+                None
+            },
+        };
         Self { maybe_cpu }
     }
 
     // Refresh and poll the current temperature
-    pub fn get_temp(&mut self, farenheit: bool) -> Option<u8> {
+    pub fn get_temp(&mut self, farenheit: bool) -> Option<f32> {
         self.maybe_cpu.as_mut().map(|cpu| {
-            cpu.refresh();
-            match cpu.temperature() {
+            let temp = match cpu {
+                CpuSource::Component(c) => {
+                    c.refresh();
+                    c.temperature()
+                },
+                CpuSource::Sensor(s) => s.read(),
+            };
+            match temp {
                 Some(mut temp) => {
                     if farenheit {
                         temp = temp * 9. / 5. + 32.;
                     }
-                    temp as u8
+                    temp
                 },
-                None => 0,
+                None => 0.,
             }
         })
     }
@@ -170,35 +446,34 @@ impl CpuTemp {
 pub fn apply_system(
     board: &mut dyn Board,
     farenheit: bool,
-    cpu: &mut Either<CpuTemp, u8>,
-    gpu: &Either<GpuTemp, u8>,
-    download: Option<f32>,
+    ranges: &RangeConfig,
+    cpu: &mut Either<CpuTemp, f32>,
+    gpu: &Either<GpuTemp, f32>,
+    download: &mut Either<NetworkRate, f32>,
 ) -> Result<(), Box<dyn Error>> {
     let system_info = board
         .as_system_info()
         .ok_or("board does not support system info")?;
 
-    let mut cpu_temp = cpu
+    let cpu_temp = cpu
         .as_mut()
         .map_left(|c| c.get_temp(farenheit).unwrap_or_default())
         .map_right(|v| *v)
         .into_inner();
-    if cpu_temp >= 100 {
-        eprintln!("warning: actual cpu temperature at {cpu_temp}, clamping to 99");
-        cpu_temp = 99;
-    }
+    let (cpu_temp, _) = ranges.cpu.fit("cpu", cpu_temp, farenheit);
 
-    let mut gpu_temp = gpu
+    let gpu_temp = gpu
         .as_ref()
         .map_left(|g| g.get_temp(farenheit).unwrap_or_default())
         .map_right(|v| *v)
         .into_inner();
-    if gpu_temp >= 100 {
-        eprintln!("warning: actual gpu temerature at {gpu_temp}. clamping to 99");
-        gpu_temp = 99;
-    }
+    let (gpu_temp, _) = ranges.gpu.fit("gpu", gpu_temp, farenheit);
 
-    let download = download.unwrap_or_default();
+    let download = download
+        .as_mut()
+        .map_left(|n| n.get_rate().unwrap_or_default())
+        .map_right(|v| *v)
+        .into_inner();
 
     system_info
         .set_system_info(cpu_temp, gpu_temp, download)
@@ -209,3 +484,66 @@ pub fn apply_system(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hwmon::tests::fake_hwmon;
+
+    #[test]
+    fn parse_gpu_device() {
+        assert_eq!("1".parse(), Ok(GpuDevice::Index(1)));
+        assert_eq!(
+            "amdgpu:junction".parse(),
+            Ok(GpuDevice::Hwmon {
+                driver: Some("amdgpu".into()),
+                sensor: Some(GpuSensor::Junction)
+            })
+        );
+        assert_eq!(
+            "hwmon".parse(),
+            Ok(GpuDevice::Hwmon {
+                driver: None,
+                sensor: None
+            })
+        );
+        assert_eq!("hwmon".parse::<GpuDevice>().unwrap().to_string(), "hwmon");
+        assert!("nouveau".parse::<GpuDevice>().is_err());
+        assert!("amdgpu:hot".parse::<GpuDevice>().is_err());
+    }
+
+    #[test]
+    fn hwmon_gpu() {
+        let root = fake_hwmon(
+            "hwmon-gpu",
+            &[
+                ("k10temp", &[(Some("Tctl"), 61000)]),
+                (
+                    "amdgpu",
+                    &[
+                        (Some("edge"), 45000),
+                        (Some("junction"), 52000),
+                        (Some("mem"), 60000),
+                    ],
+                ),
+                ("xe", &[(Some("pkg"), 70000), (Some("vram"), 80000)]),
+                ("radeon", &[(None, 40000)]),
+            ],
+        );
+
+        let temp = |device: &str| {
+            GpuTemp::with_hwmon_root(&device.parse().unwrap(), &root).get_temp(false)
+        };
+        assert_eq!(temp("hwmon"), Some(45.));
+        assert_eq!(temp("amdgpu:junction"), Some(52.));
+        assert_eq!(temp("amdgpu:mem"), Some(60.));
+        assert_eq!(temp("xe:junction"), Some(70.));
+        assert_eq!(temp("xe:mem"), Some(80.));
+        // unlabeled inputs are only used when no sensor is asked for
+        assert_eq!(temp("radeon"), Some(40.));
+        assert_eq!(temp("radeon:junction"), None);
+        assert_eq!(temp("i915"), None);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -5,24 +5,43 @@ use std::path::PathBuf;
 use std::str::FromStr;
 
 use bpaf::{Bpaf, Parser};
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
 use image::codecs::gif::GifDecoder;
 use image::codecs::png::PngDecoder;
 use image::codecs::webp::WebPDecoder;
 use image::AnimationDecoder;
 use zoom_sync_core::Board;
 
+use crate::clock::{TimeAt, TimeOffset};
+use crate::config::Config;
 use crate::detection::{board_kind, BoardKind};
-use crate::info::{apply_system, cpu_mode, gpu_mode, CpuMode, GpuMode};
+use crate::info::{
+    apply_system, cpu_mode, download_mode, gpu_mode, CpuMode, DownloadMode, GpuMode,
+};
 use crate::media::{encode_gif, encode_image};
 use crate::screen::{apply_screen, screen_args, ScreenArgs};
-use crate::weather::{apply_weather, weather_args, WeatherArgs};
+use crate::tray::TimerRequest;
+use crate::weather::{apply_weather, weather_args, IconMap, WeatherArgs};
 
+mod alerts;
+mod carousel;
+mod clock;
 mod config;
 mod detection;
+mod hwmon;
 mod info;
 mod lock;
 mod media;
+mod network;
+mod rules;
+mod schedule;
 mod screen;
+mod scripts;
+mod sensors;
+mod sources;
+mod temperature;
+mod timer;
 mod tray;
 mod weather;
 
@@ -41,7 +60,20 @@ No effect on any manually provided data.",
 enum SetCommand {
     /// Sync time to system clock
     #[bpaf(command)]
-    Time,
+    Time {
+        /// Use 12-hour time
+        #[bpaf(long("12hr"))]
+        use_12hr: bool,
+        /// Time to set instead of the current time, either RFC3339 or HH:MM[:SS] for today
+        #[bpaf(long, argument("TIME"), optional)]
+        at: Option<TimeAt>,
+        /// Timezone to show instead of local time, ie America/New_York
+        #[bpaf(long, argument("TZ"), optional)]
+        tz: Option<Tz>,
+        /// Offset to add to the time, ie 5m or -1h
+        #[bpaf(long, argument("DURATION"), optional)]
+        offset: Option<TimeOffset>,
+    },
     /// Set weather data
     #[bpaf(command)]
     Weather {
@@ -59,9 +91,8 @@ enum SetCommand {
         cpu_mode: CpuMode,
         #[bpaf(external)]
         gpu_mode: GpuMode,
-        /// Manually set download speed
-        #[bpaf(short, long)]
-        download: Option<f32>,
+        #[bpaf(external)]
+        download_mode: DownloadMode,
     },
     /// Change current screen
     #[bpaf(command, fallback_to_usage)]
@@ -149,6 +180,11 @@ enum Command {
     /// Set specific options on the keyboard.
     /// Must not be used while zoom-sync is already running.
     Set { set_command: SetCommand },
+    /// List available temperature sensors.
+    Sensors,
+    /// Start or stop a countdown or stopwatch on the clock screen, in the running daemon if
+    /// there is one.
+    Timer { request: TimerRequest },
 }
 
 fn command() -> impl Parser<Command> {
@@ -165,15 +201,48 @@ fn command() -> impl Parser<Command> {
         .command("set")
         .help("Set specific options on the keyboard");
 
-    bpaf::construct!([tray, set]).fallback(Command::Tray)
+    let sensors = bpaf::pure(Command::Sensors)
+        .to_options()
+        .descr("List available temperature sensors and their config strings")
+        .command("sensors")
+        .help("List available temperature sensors");
+
+    let timer = bpaf::positional::<TimerRequest>("DURATION")
+        .help("Countdown duration (ie 25m), \"stopwatch\", or \"stop\" to stop the daemon's timer")
+        .map(|request| Command::Timer { request })
+        .to_options()
+        .descr(
+            "Run a countdown or stopwatch on the clock screen. \
+Sent to the running daemon if there is one, otherwise runs until it finishes or ctrl-c",
+        )
+        .command("timer")
+        .help("Run a countdown or stopwatch on the clock screen");
+
+    bpaf::construct!([tray, set, sensors, timer]).fallback(Command::Tray)
+}
+
+/// Sync the board clock to the current time in a timezone, or in local time if not given
+pub fn apply_time(
+    board: &mut dyn Board,
+    tz: Option<Tz>,
+    _12hr: bool,
+) -> Result<(), Box<dyn Error>> {
+    set_time(board, clock::now(tz), _12hr)
 }
 
-pub fn apply_time(board: &mut dyn Board, _12hr: bool) -> Result<(), Box<dyn Error>> {
-    let time = chrono::Local::now();
+/// Set the board clock to the wall time of a specific time
+pub(crate) fn set_time<Tz: TimeZone>(
+    board: &mut dyn Board,
+    time: DateTime<Tz>,
+    _12hr: bool,
+) -> Result<(), Box<dyn Error>>
+where
+    Tz::Offset: Display,
+{
     board
         .as_time()
         .ok_or("board does not support time")?
-        .set_time(time, _12hr)?;
+        .set_time(time.fixed_offset(), _12hr)?;
     println!("updated time to {time}");
     Ok(())
 }
@@ -185,28 +254,85 @@ fn main() -> Result<(), Box<dyn Error>> {
             let _lock = lock::Lock::acquire()?;
             tray::run_tray_app(cli.board)
         },
+        Command::Sensors => {
+            sensors::print_sensors();
+            Ok(())
+        },
+        Command::Timer { request } => {
+            // the daemon keeps its other updates going while the timer runs
+            if tray::send_timer(request)? {
+                println!("sent timer {request} to the running daemon");
+                return Ok(());
+            }
+            let TimerRequest::Start(mode) = request else {
+                return Err("no running daemon to stop the timer of".into());
+            };
+            let _lock = lock::Lock::acquire()?;
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(async {
+                let mut board = cli.board.as_board()?;
+                tray::run_timer(board.as_mut(), mode).await
+            })
+        },
         Command::Set { set_command } => {
+            // share the display settings of the tray
+            let config = Config::load().unwrap_or_default();
             let rt = tokio::runtime::Runtime::new()?;
             rt.block_on(async {
                 let mut board = cli.board.as_board()?;
                 match set_command {
-                    SetCommand::Time => apply_time(board.as_mut(), false),
+                    SetCommand::Time {
+                        use_12hr,
+                        at,
+                        tz,
+                        offset,
+                    } => {
+                        if at.is_none() {
+                            // start on a second boundary, so the seconds match the system clock
+                            tokio::time::sleep(clock::until_next_second(&clock::now(tz))).await;
+                        }
+                        let time = clock::resolve(at, tz, offset)?;
+                        set_time(board.as_mut(), time, use_12hr)
+                    },
                     SetCommand::Weather {
                         farenheit,
                         mut weather_args,
-                    } => apply_weather(board.as_mut(), &mut weather_args, farenheit).await,
+                    } => {
+                        apply_weather(
+                            board.as_mut(),
+                            &mut weather_args,
+                            farenheit,
+                            &config.general.ranges,
+                            &IconMap::from_config(&config.weather),
+                            config
+                                .weather
+                                .latitude
+                                .zip(config.weather.longitude)
+                                .map(|(lat, long)| (lat as f32, long as f32)),
+                            Default::default(),
+                        )
+                        .await
+                    },
                     SetCommand::System {
                         farenheit,
                         cpu_mode,
                         gpu_mode,
-                        download,
-                    } => apply_system(
-                        board.as_mut(),
-                        farenheit,
-                        &mut cpu_mode.either(),
-                        &gpu_mode.either(),
-                        download,
-                    ),
+                        download_mode,
+                    } => {
+                        let mut download = download_mode.either();
+                        if download.is_left() {
+                            // sample network counters over a short window
+                            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        }
+                        apply_system(
+                            board.as_mut(),
+                            farenheit,
+                            &config.general.ranges,
+                            &mut cpu_mode.either(),
+                            &gpu_mode.either(),
+                            &mut download,
+                        )
+                    },
                     SetCommand::Screen(args) => apply_screen(&args, board.as_mut()),
                     SetCommand::Image(args) => match args {
                         SetMediaArgs::Set { nearest, path, bg } => {
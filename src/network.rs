@@ -0,0 +1,262 @@
+//! Utilities for monitoring network throughput
+
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use sysinfo::Networks;
+use zoom65v3::float::DumbFloat16;
+
+/// Which direction of traffic to report
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateDirection {
+    #[default]
+    Download,
+    Upload,
+}
+
+impl FromStr for RateDirection {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "download" | "down" | "rx" => Ok(Self::Download),
+            "upload" | "up" | "tx" => Ok(Self::Upload),
+            _ => Err(format!(
+                "invalid direction '{s}', must be download or upload"
+            )),
+        }
+    }
+}
+
+/// Base unit for the reported rate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateUnit {
+    /// Kilobytes per second
+    #[default]
+    #[serde(rename = "kb/s")]
+    KiloBytes,
+    /// Megabytes per second
+    #[serde(rename = "mb/s")]
+    MegaBytes,
+    /// Megabits per second
+    #[serde(rename = "mbit/s")]
+    MegaBits,
+}
+
+impl RateUnit {
+    /// Number of bytes per second in one of this unit
+    fn bytes(&self) -> f64 {
+        match self {
+            RateUnit::KiloBytes => 1e3,
+            RateUnit::MegaBytes => 1e6,
+            RateUnit::MegaBits => 1e6 / 8.,
+        }
+    }
+}
+
+impl RateUnit {
+    /// Name of the unit after scaling up by 1000 a number of times
+    pub fn scaled(&self, steps: u32) -> String {
+        const PREFIXES: [&str; 5] = ["k", "m", "g", "t", "p"];
+        let (start, suffix) = match self {
+            RateUnit::KiloBytes => (0, "b/s"),
+            RateUnit::MegaBytes => (1, "b/s"),
+            RateUnit::MegaBits => (1, "bit/s"),
+        };
+        let prefix = PREFIXES[(start + steps as usize).min(PREFIXES.len() - 1)];
+        format!("{prefix}{suffix}")
+    }
+}
+
+impl FromStr for RateUnit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kb/s" | "kb" => Ok(Self::KiloBytes),
+            "mb/s" | "mb" => Ok(Self::MegaBytes),
+            "mbit/s" | "mbit" | "mbps" => Ok(Self::MegaBits),
+            _ => Err(format!(
+                "invalid unit '{s}', must be one of: kb/s, mb/s, mbit/s"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for RateUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RateUnit::KiloBytes => "kb/s",
+            RateUnit::MegaBytes => "mb/s",
+            RateUnit::MegaBits => "mbit/s",
+        })
+    }
+}
+
+/// Convert a raw byte rate into the display unit. When auto scaling, the value is divided by 1000
+/// until it fits within the range the keyboard can display, otherwise it is clamped. Returns the
+/// value and the number of times it was scaled, see [`RateUnit::scaled`].
+pub fn scale_rate(bytes_per_sec: f64, unit: RateUnit, auto_scale: bool) -> (f32, u32) {
+    let mut value = bytes_per_sec / unit.bytes();
+    let mut steps = 0;
+    if auto_scale {
+        while value > DumbFloat16::MAX_F32 as f64 {
+            value /= 1000.;
+            steps += 1;
+        }
+    }
+    let value = (value as f32).clamp(DumbFloat16::MIN_F32, DumbFloat16::MAX_F32);
+    (value, steps)
+}
+
+/// Find the interface used by the default route from the contents of `/proc/net/route`
+pub fn parse_default_route(route_table: &str) -> Option<String> {
+    route_table.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let iface = fields.next()?;
+        let destination = fields.next()?;
+        (destination == "00000000").then(|| iface.to_string())
+    })
+}
+
+/// Helper struct to track network throughput between samples
+pub struct NetworkRate {
+    networks: Networks,
+    interface: Option<String>,
+    direction: RateDirection,
+    unit: RateUnit,
+    auto_scale: bool,
+    /// Times the last rate was scaled up
+    steps: u32,
+    /// Interface name, counter value, and time of the last sample
+    last: Option<(String, u64, Instant)>,
+}
+
+impl NetworkRate {
+    /// Construct a new rate monitor, optionally for a specific interface. If no interface is
+    /// given, the interface for the default route is used and re-resolved on every sample.
+    pub fn new(
+        interface: Option<&str>,
+        direction: RateDirection,
+        unit: RateUnit,
+        auto_scale: bool,
+    ) -> Self {
+        let mut this = Self {
+            networks: Networks::new_with_refreshed_list(),
+            interface: interface.map(ToString::to_string),
+            direction,
+            unit,
+            auto_scale,
+            steps: 0,
+            last: None,
+        };
+
+        match this.resolve_interface() {
+            Some(iface) => {
+                this.last = this.sample(&iface).map(|v| (iface, v, Instant::now()));
+            },
+            None => eprintln!("warning: no network interface found"),
+        }
+        if let (Some(iface), None) = (interface, &this.last) {
+            eprintln!("warning: network interface {iface} not found");
+            let names: Vec<_> = this.networks.list().keys().collect();
+            if !names.is_empty() {
+                eprintln!("  available interfaces:");
+                for name in names {
+                    eprintln!("    - {name}");
+                }
+            }
+        }
+
+        this
+    }
+
+    /// Get the configured interface, or the one the default route currently goes through
+    fn resolve_interface(&self) -> Option<String> {
+        if self.interface.is_some() {
+            return self.interface.clone();
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(iface) = std::fs::read_to_string("/proc/net/route")
+            .ok()
+            .as_deref()
+            .and_then(parse_default_route)
+        {
+            return Some(iface);
+        }
+
+        // Otherwise, guess by the busiest non-loopback interface
+        self.networks
+            .list()
+            .iter()
+            .filter(|(name, _)| !name.starts_with("lo") && !name.starts_with("Loopback"))
+            .max_by_key(|(_, data)| data.total_received() + data.total_transmitted())
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Read the total byte counter for an interface
+    fn sample(&self, iface: &str) -> Option<u64> {
+        self.networks.get(iface).map(|data| match self.direction {
+            RateDirection::Download => data.total_received(),
+            RateDirection::Upload => data.total_transmitted(),
+        })
+    }
+
+    /// Refresh counters and compute the rate since the last sample, in the configured unit
+    pub fn get_rate(&mut self) -> Option<f32> {
+        self.networks.refresh(true);
+        let iface = self.resolve_interface()?;
+        let total = self.sample(&iface)?;
+        let now = Instant::now();
+
+        let rate = match self.last.take() {
+            // Only compare against the previous sample from the same interface
+            Some((last_iface, last_total, last_time)) if last_iface == iface => {
+                let elapsed = now.duration_since(last_time).as_secs_f64();
+                let bytes = total.saturating_sub(last_total) as f64;
+                (elapsed > 0.).then(|| bytes / elapsed)
+            },
+            _ => None,
+        };
+        self.last = Some((iface, total, now));
+
+        let (value, steps) = scale_rate(rate.unwrap_or_default(), self.unit, self.auto_scale);
+        if steps != self.steps {
+            println!("network rate now shown in {}", self.unit.scaled(steps));
+            self.steps = steps;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_route() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+            wlan0\t0000A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\n\
+            wlan0\t00000000\t0100A8C0\t0003\t0\t0\t600\t00000000\n";
+        assert_eq!(parse_default_route(table).as_deref(), Some("wlan0"));
+        assert_eq!(parse_default_route("Iface\tDestination\n"), None);
+    }
+
+    #[test]
+    fn scaling() {
+        assert_eq!(scale_rate(12_000., RateUnit::KiloBytes, true), (12., 0));
+        assert_eq!(scale_rate(1_000_000., RateUnit::MegaBits, false), (8., 0));
+        // 100 MB/s does not fit as kb/s, scale up to mb/s
+        assert_eq!(
+            scale_rate(100_000_000., RateUnit::KiloBytes, true),
+            (100., 1)
+        );
+        assert_eq!(RateUnit::KiloBytes.scaled(1), "mb/s");
+        assert_eq!(RateUnit::MegaBits.scaled(1), "gbit/s");
+        assert_eq!(
+            scale_rate(100_000_000., RateUnit::KiloBytes, false),
+            (DumbFloat16::MAX_F32, 0)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Rules reacting to system state, evaluated against snapshots of metrics, processes and idle
+//! time
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::FeatureToggles;
+use crate::sources::Field;
+
+/// When a rule triggers, then what it does until the condition clears
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub name: String,
+    pub when: Condition,
+    /// How long the condition must hold before triggering, ie "30s"
+    #[serde(default, rename = "for", with = "humantime_serde")]
+    pub hold: Duration,
+    /// How long the condition must be clear before reverting
+    #[serde(default, with = "humantime_serde")]
+    pub clear_for: Duration,
+    pub then: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    /// Metric past a threshold, ie { metric = "cpu", above = 85 }. While triggered, the
+    /// thresholds are relaxed by the hysteresis so small dips don't clear it.
+    Metric {
+        metric: Field,
+        #[serde(default)]
+        above: Option<f32>,
+        #[serde(default)]
+        below: Option<f32>,
+        #[serde(default)]
+        hysteresis: f32,
+    },
+    /// Running process, ie { process = "cargo" }
+    Process { process: String },
+    /// No user input for a while, ie { idle = "10m" }
+    Idle {
+        #[serde(with = "humantime_serde")]
+        idle: Duration,
+    },
+}
+
+impl Condition {
+    /// Check the condition, or `None` when the snapshot doesn't have what it needs
+    fn check(&self, snapshot: &Snapshot, active: bool) -> Option<bool> {
+        match self {
+            Condition::Metric {
+                metric,
+                above,
+                below,
+                hysteresis,
+            } => {
+                let value = *snapshot.metrics.get(metric)?;
+                let margin = if active { *hysteresis } else { 0. };
+                let checks = [
+                    above.map(|t| value > t - margin),
+                    below.map(|t| value < t + margin),
+                ];
+                let mut checks = checks.into_iter().flatten().peekable();
+                Some(checks.peek().is_some() && checks.all(|c| c))
+            },
+            Condition::Process { process } => {
+                Some(snapshot.processes.contains(&process_name(process)))
+            },
+            Condition::Idle { idle } => snapshot.idle.map(|time| time >= *idle),
+        }
+    }
+}
+
+/// What a rule does while triggered. Everything except the notification is reverted when the
+/// rule clears.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Action {
+    /// Screen to show
+    pub screen: Option<String>,
+    /// Image to upload, showing the image screen unless another screen is set
+    pub image: Option<PathBuf>,
+    /// Notification to show
+    pub notify: Option<String>,
+    #[serde(flatten)]
+    pub toggles: FeatureToggles,
+}
+
+/// System state to evaluate rules against
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Latest values of the display fields, in the units shown on the keyboard
+    pub metrics: BTreeMap<Field, f32>,
+    /// Names of running processes, normalized with [`process_name`]
+    pub processes: BTreeSet<String>,
+    /// Time since the last user input, if known
+    pub idle: Option<Duration>,
+}
+
+/// Normalize a process name for matching, ignoring case and the windows executable extension
+pub fn process_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+/// Change of a rule after an update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// Rule at this index triggered, so its action should be applied
+    Trigger(usize),
+    /// Rule at this index cleared, so its action should be reverted
+    Revert(usize),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Status {
+    active: bool,
+    /// When the condition started to differ from the active state
+    pending: Option<Instant>,
+}
+
+/// Tracks which rules are triggered, and conditions waiting out their delay
+#[derive(Debug, Clone, Default)]
+pub struct RuleState {
+    status: Vec<Status>,
+}
+
+impl RuleState {
+    /// Evaluate the rules against a snapshot, returning the rules that triggered or cleared
+    pub fn update(&mut self, rules: &[Rule], snapshot: &Snapshot, now: Instant) -> Vec<Transition> {
+        self.status.resize(rules.len(), Status::default());
+        let mut transitions = Vec::new();
+        for (i, (rule, status)) in rules.iter().zip(&mut self.status).enumerate() {
+            let Some(matches) = rule.when.check(snapshot, status.active) else {
+                continue;
+            };
+            if matches == status.active {
+                status.pending = None;
+                continue;
+            }
+            let since = *status.pending.get_or_insert(now);
+            let delay = if status.active {
+                rule.clear_for
+            } else {
+                rule.hold
+            };
+            if now.duration_since(since) >= delay {
+                *status = Status {
+                    active: matches,
+                    pending: None,
+                };
+                transitions.push(if matches {
+                    Transition::Trigger(i)
+                } else {
+                    Transition::Revert(i)
+                });
+            }
+        }
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Config {
+        rules: Vec<Rule>,
+    }
+
+    #[test]
+    fn rules() {
+        let rules = toml::from_str::<Config>(
+            r#"
+            [[rules]]
+            name = "hot"
+            when = { metric = "cpu", above = 85, hysteresis = 5 }
+            for = "30s"
+            then = { screen = "cpu", notify = "cpu is hot" }
+
+            [[rules]]
+            name = "build"
+            when = { process = "cargo" }
+            clear_for = "10s"
+            then = { screen = "meletrix", weather = false }
+
+            [[rules]]
+            name = "away"
+            when = { idle = "10m" }
+            then = { image = "away.png", carousel = false }
+            "#,
+        )
+        .unwrap()
+        .rules;
+        assert_eq!(rules[1].then.toggles.weather, Some(false));
+
+        let start = Instant::now();
+        let after = |secs| start + Duration::from_secs(secs);
+        let snapshot = |cpu: f32, processes: &[&str], idle: Option<u64>| Snapshot {
+            metrics: [(Field::Cpu, cpu)].into(),
+            processes: processes.iter().map(|p| process_name(p)).collect(),
+            idle: idle.map(Duration::from_secs),
+        };
+        let mut state = RuleState::default();
+
+        // the condition must hold for 30s
+        assert!(state
+            .update(&rules, &snapshot(90., &[], None), after(0))
+            .is_empty());
+        assert!(state
+            .update(&rules, &snapshot(90., &[], None), after(20))
+            .is_empty());
+        assert_eq!(
+            state.update(&rules, &snapshot(90., &[], None), after(30)),
+            [Transition::Trigger(0)]
+        );
+        // hysteresis keeps it triggered through small dips
+        assert!(state
+            .update(&rules, &snapshot(82., &[], None), after(35))
+            .is_empty());
+        assert_eq!(
+            state.update(&rules, &snapshot(79., &[], None), after(40)),
+            [Transition::Revert(0)]
+        );
+
+        // processes trigger right away, and clear after 10s without flapping
+        let cargo = snapshot(50., &["Cargo.exe"], None);
+        let idle = |secs| snapshot(50., &[], Some(secs));
+        assert_eq!(
+            state.update(&rules, &cargo, after(50)),
+            [Transition::Trigger(1)]
+        );
+        assert!(state
+            .update(&rules, &snapshot(50., &[], None), after(55))
+            .is_empty());
+        assert!(state.update(&rules, &cargo, after(58)).is_empty());
+        assert!(state
+            .update(&rules, &snapshot(50., &[], None), after(60))
+            .is_empty());
+        assert_eq!(
+            state.update(&rules, &snapshot(50., &[], None), after(70)),
+            [Transition::Revert(1)]
+        );
+
+        assert_eq!(
+            state.update(&rules, &idle(600), after(80)),
+            [Transition::Trigger(2)]
+        );
+        assert_eq!(
+            state.update(&rules, &idle(1), after(85)),
+            [Transition::Revert(2)]
+        );
+
+        // unknown values leave rules as they are
+        assert!(state
+            .update(&rules, &Snapshot::default(), after(90))
+            .is_empty());
+    }
+}
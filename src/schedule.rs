@@ -0,0 +1,192 @@
+//! Time-of-day schedule selecting the screen, theme and feature toggles
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use chrono::{Datelike, Days, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::config::FeatureToggles;
+
+/// How often the daemon checks the schedule
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Schedule entry, active from `start` until `end` on the given days. Ranges ending before they
+/// start run past midnight, and equal times cover the whole day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    /// Days the entry starts on, ie ["sat", "sun"]. Every day if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    /// Local time the entry starts, ie "07:00"
+    #[serde(default)]
+    pub start: NaiveTime,
+    /// Local time the entry ends, ie "09:00"
+    #[serde(default)]
+    pub end: NaiveTime,
+    /// Screen to show
+    pub screen: Option<String>,
+    /// Screen theme to use, ie "blue" or "pink"
+    pub theme: Option<String>,
+    #[serde(flatten)]
+    pub toggles: FeatureToggles,
+}
+
+impl ScheduleEntry {
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let (day, inside) = if self.start < self.end {
+            (now.weekday(), self.start <= time && time < self.end)
+        } else if time >= self.start {
+            (now.weekday(), true)
+        } else {
+            // still inside the range that started the day before
+            (now.weekday().pred(), time < self.end)
+        };
+        inside && (self.days.is_empty() || self.days.contains(&day))
+    }
+}
+
+impl Display for ScheduleEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )?;
+        if !self.days.is_empty() {
+            let days: Vec<_> = self.days.iter().map(|d| d.to_string()).collect();
+            write!(f, " on {}", days.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Index of the entry active at a time, where earlier entries take priority
+pub fn active(entries: &[ScheduleEntry], now: NaiveDateTime) -> Option<usize> {
+    entries.iter().position(|entry| entry.contains(now))
+}
+
+/// Next time the active entry changes, within the next week
+pub fn next_boundary(entries: &[ScheduleEntry], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let current = active(entries, now);
+    let mut times: Vec<_> = entries
+        .iter()
+        .flat_map(|entry| [entry.start, entry.end])
+        .chain([NaiveTime::MIN])
+        .collect();
+    times.sort();
+    times.dedup();
+    (0..=7)
+        .filter_map(|days| now.date().checked_add_days(Days::new(days)))
+        .flat_map(|date| times.iter().map(move |time| date.and_time(*time)))
+        .filter(|time| *time > now)
+        .find(|time| active(entries, *time) != current)
+}
+
+/// Tracks the applied schedule entry, and manual changes that hold it off
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    active: Option<usize>,
+    hold_until: Option<NaiveDateTime>,
+}
+
+impl Schedule {
+    /// Keep a manual change until the next boundary of the schedule
+    pub fn hold(&mut self, entries: &[ScheduleEntry], now: NaiveDateTime) {
+        self.hold_until = Some(next_boundary(entries, now).unwrap_or(NaiveDateTime::MAX));
+    }
+
+    /// Check the schedule, returning the entry to apply when a different one becomes active or a
+    /// hold ends. Outside of every entry, the last applied settings are kept.
+    pub fn update<'a>(
+        &mut self,
+        entries: &'a [ScheduleEntry],
+        now: NaiveDateTime,
+    ) -> Option<&'a ScheduleEntry> {
+        if self.hold_until.is_some_and(|until| now < until) {
+            return None;
+        }
+        let held = self.hold_until.take().is_some();
+        let active = active(entries, now);
+        let changed = held || active != self.active;
+        self.active = active;
+        active.filter(|_| changed).map(|i| &entries[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn schedule() {
+        #[derive(Deserialize)]
+        struct Config {
+            schedule: Vec<ScheduleEntry>,
+        }
+        let entries = toml::from_str::<Config>(
+            r#"
+            [[schedule]]
+            days = ["sat", "sun"]
+            screen = "image"
+
+            [[schedule]]
+            start = "07:00"
+            end = "09:00"
+            screen = "weather"
+            theme = "blue"
+
+            [[schedule]]
+            days = ["mon", "tue", "wed", "thu", "fri"]
+            start = "09:00"
+            end = "17:30"
+            screen = "cpu"
+            weather = false
+
+            [[schedule]]
+            start = "22:00"
+            end = "07:00"
+            screen = "meletrix"
+            "#,
+        )
+        .unwrap()
+        .schedule;
+        let screen = |s| active(&entries, at(s)).and_then(|i| entries[i].screen.as_deref());
+
+        // 2024-05-03 is a friday
+        assert_eq!(screen("2024-05-03 06:59"), Some("meletrix"));
+        assert_eq!(screen("2024-05-03 07:00"), Some("weather"));
+        assert_eq!(screen("2024-05-03 12:00"), Some("cpu"));
+        assert_eq!(screen("2024-05-03 18:00"), None);
+        assert_eq!(screen("2024-05-03 23:00"), Some("meletrix"));
+        assert_eq!(screen("2024-05-04 07:30"), Some("image"));
+        assert_eq!(entries[2].to_string(), "09:00-17:30 on Mon,Tue,Wed,Thu,Fri");
+
+        // a manual change holds until the next boundary
+        let mut schedule = Schedule::default();
+        let update = |schedule: &mut Schedule, s| schedule.update(&entries, at(s)).cloned();
+        assert_eq!(
+            update(&mut schedule, "2024-05-03 08:00"),
+            Some(entries[1].clone())
+        );
+        assert_eq!(update(&mut schedule, "2024-05-03 08:10"), None);
+        schedule.hold(&entries, at("2024-05-03 08:20"));
+        assert_eq!(update(&mut schedule, "2024-05-03 08:59"), None);
+        assert_eq!(
+            update(&mut schedule, "2024-05-03 09:00"),
+            Some(entries[2].clone())
+        );
+        assert_eq!(update(&mut schedule, "2024-05-03 18:00"), None);
+        // friday night runs into the weekend at midnight
+        assert_eq!(
+            next_boundary(&entries, at("2024-05-03 23:00")),
+            Some(at("2024-05-04 00:00"))
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//! User scripts in ~/.config/zoom-sync/scripts/*.rhai, run on ticks and events with a sandboxed
+//! api for reading the system state and driving the keyboard
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::TimeAt;
+use crate::sources::Field;
+use crate::weather::WeatherData;
+
+/// Coarsest file timestamp resolution expected, below which changes may not show in a new time
+const TIMESTAMP_RESOLUTION: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptsConfig {
+    /// Run scripts from the scripts directory next to the config file (default off)
+    pub enabled: bool,
+    /// How often to run the `on_tick` function of each script
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// Most operations a script may run per call, stopping runaway loops
+    pub max_operations: u64,
+}
+
+impl Default for ScriptsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Duration::from_secs(5),
+            max_operations: 100_000,
+        }
+    }
+}
+
+/// Event a script function runs on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Every `scripts.interval`
+    Tick,
+    /// After the keyboard connects
+    Connect,
+    /// After new weather is fetched
+    Weather,
+}
+
+impl Event {
+    fn function(self) -> &'static str {
+        match self {
+            Event::Tick => "on_tick",
+            Event::Connect => "on_connect",
+            Event::Weather => "on_weather",
+        }
+    }
+}
+
+/// State that scripts can read
+#[derive(Debug, Clone, Default)]
+pub struct ScriptInput {
+    /// Latest values of the display fields, in the units shown on the keyboard
+    pub metrics: BTreeMap<Field, f32>,
+    /// Weather of the location shown on the keyboard, in the units shown on the keyboard
+    pub weather: Option<WeatherData>,
+    pub screen: Option<String>,
+}
+
+/// Change requested by a script, applied by the daemon after the script returns
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    SetScreen(String),
+    SetSystemInfo {
+        cpu: f32,
+        gpu: f32,
+        download: f32,
+    },
+    SetWeather {
+        wmo: u8,
+        is_day: bool,
+        current: f32,
+        min: f32,
+        max: f32,
+    },
+    /// Show a time on the clock, or the real time if not given
+    SetTime(Option<TimeAt>),
+    UploadImage(PathBuf),
+    UploadGif(PathBuf),
+    Notify(String),
+}
+
+struct Script {
+    modified: Option<SystemTime>,
+    /// Compiled script, or `None` if it failed to compile
+    ast: Option<AST>,
+    scope: Scope<'static>,
+    /// Value bound to `this` in script functions, kept between calls
+    state: Dynamic,
+    /// Last reported error, so a failing script doesn't report on every tick
+    last_error: Option<String>,
+}
+
+/// Loaded scripts and the engine running them
+pub struct Scripts {
+    engine: Engine,
+    dir: PathBuf,
+    /// Modification time of the directory when it was last listed, if it was old enough that a
+    /// later change would show as a different time
+    dir_modified: Option<SystemTime>,
+    scripts: BTreeMap<PathBuf, Script>,
+    input: Rc<RefCell<ScriptInput>>,
+    actions: Rc<RefCell<Vec<ScriptAction>>>,
+}
+
+/// Convert a script number, which may be an integer or a float
+fn number(value: Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    match value.as_float() {
+        Ok(float) => Ok(float as f32),
+        Err(_) => value
+            .as_int()
+            .map(|int| int as f32)
+            .map_err(|t| format!("expected a number, got {t}").into()),
+    }
+}
+
+/// Resolve a file a script refers to, relative to the scripts directory and not outside of it
+fn script_file(dir: &Path, path: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+    let file = dir
+        .join(path)
+        .canonicalize()
+        .map_err(|e| format!("{path}: {e}"))?;
+    let dir = dir.canonicalize().map_err(|e| format!("{path}: {e}"))?;
+    if file.starts_with(&dir) {
+        Ok(file)
+    } else {
+        Err(format!("{path} is outside the scripts directory").into())
+    }
+}
+
+impl Scripts {
+    /// Build the engine for scripts in a directory
+    pub fn new(dir: PathBuf, config: &ScriptsConfig) -> Self {
+        let input = Rc::new(RefCell::new(ScriptInput::default()));
+        let actions = Rc::new(RefCell::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        // no file access through imports, or evaluating generated code
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.set_max_operations(config.max_operations);
+        engine.set_max_call_levels(32);
+        engine.set_max_string_size(10_000);
+        engine.set_max_array_size(1_000);
+        engine.set_max_map_size(1_000);
+        engine.on_print(|s| println!("script: {s}"));
+        engine.on_debug(|s, _, pos| println!("script debug at {pos}: {s}"));
+
+        let state = input.clone();
+        engine.register_fn("metric", move |name: &str| -> Dynamic {
+            let field = Field::deserialize(
+                IntoDeserializer::<serde::de::value::Error>::into_deserializer(name),
+            );
+            field
+                .ok()
+                .and_then(|field| state.borrow().metrics.get(&field).copied())
+                .map_or(Dynamic::UNIT, |value| Dynamic::from_float(value.into()))
+        });
+        let state = input.clone();
+        engine.register_fn("weather", move || -> Dynamic {
+            let Some(data) = state.borrow().weather.clone() else {
+                return Dynamic::UNIT;
+            };
+            let mut map = Map::new();
+            map.insert("wmo".into(), (data.wmo as i64).into());
+            map.insert("is_day".into(), data.is_day.into());
+            map.insert("current".into(), Dynamic::from_float(data.current.into()));
+            map.insert("min".into(), Dynamic::from_float(data.min.into()));
+            map.insert("max".into(), Dynamic::from_float(data.max.into()));
+            map.into()
+        });
+        let state = input.clone();
+        engine.register_fn("screen", move || -> Dynamic {
+            state
+                .borrow()
+                .screen
+                .clone()
+                .map_or(Dynamic::UNIT, Dynamic::from)
+        });
+
+        let queue = actions.clone();
+        engine.register_fn("set_screen", move |id: &str| {
+            queue.borrow_mut().push(ScriptAction::SetScreen(id.into()));
+        });
+        let queue = actions.clone();
+        engine.register_fn(
+            "set_system_info",
+            move |cpu: Dynamic,
+                  gpu: Dynamic,
+                  download: Dynamic|
+                  -> Result<(), Box<EvalAltResult>> {
+                let action = ScriptAction::SetSystemInfo {
+                    cpu: number(cpu)?,
+                    gpu: number(gpu)?,
+                    download: number(download)?,
+                };
+                queue.borrow_mut().push(action);
+                Ok(())
+            },
+        );
+        let queue = actions.clone();
+        engine.register_fn(
+            "set_weather",
+            move |wmo: i64,
+                  is_day: bool,
+                  current: Dynamic,
+                  min: Dynamic,
+                  max: Dynamic|
+                  -> Result<(), Box<EvalAltResult>> {
+                let action = ScriptAction::SetWeather {
+                    wmo: u8::try_from(wmo).map_err(|_| format!("invalid wmo code {wmo}"))?,
+                    is_day,
+                    current: number(current)?,
+                    min: number(min)?,
+                    max: number(max)?,
+                };
+                queue.borrow_mut().push(action);
+                Ok(())
+            },
+        );
+        let queue = actions.clone();
+        engine.register_fn("set_time", move || {
+            queue.borrow_mut().push(ScriptAction::SetTime(None));
+        });
+        let queue = actions.clone();
+        engine.register_fn(
+            "set_time",
+            move |time: &str| -> Result<(), Box<EvalAltResult>> {
+                let time = time.parse::<TimeAt>()?;
+                queue.borrow_mut().push(ScriptAction::SetTime(Some(time)));
+                Ok(())
+            },
+        );
+        let (queue, base) = (actions.clone(), dir.clone());
+        engine.register_fn(
+            "upload_image",
+            move |path: &str| -> Result<(), Box<EvalAltResult>> {
+                let path = script_file(&base, path)?;
+                queue.borrow_mut().push(ScriptAction::UploadImage(path));
+                Ok(())
+            },
+        );
+        let (queue, base) = (actions.clone(), dir.clone());
+        engine.register_fn(
+            "upload_gif",
+            move |path: &str| -> Result<(), Box<EvalAltResult>> {
+                let path = script_file(&base, path)?;
+                queue.borrow_mut().push(ScriptAction::UploadGif(path));
+                Ok(())
+            },
+        );
+        let queue = actions.clone();
+        engine.register_fn("notify", move |body: &str| {
+            queue.borrow_mut().push(ScriptAction::Notify(body.into()));
+        });
+
+        Self {
+            engine,
+            dir,
+            dir_modified: None,
+            scripts: BTreeMap::new(),
+            input,
+            actions,
+        }
+    }
+
+    /// Most operations a script may run per call, which needs a new engine to change
+    pub fn max_operations(&self) -> u64 {
+        self.engine.max_operations()
+    }
+
+    /// Load new and changed scripts, and drop deleted ones. Returns compile errors to report.
+    /// The directory is only listed again when its modification time changes, and scripts are
+    /// only recompiled when theirs does.
+    pub fn reload(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let dir_modified = std::fs::metadata(&self.dir).and_then(|m| m.modified()).ok();
+        let paths: Vec<PathBuf> = if dir_modified.is_some() && dir_modified == self.dir_modified {
+            self.scripts.keys().cloned().collect()
+        } else {
+            // a change within the timestamp resolution of this listing wouldn't show, so a
+            // recent time isn't trusted
+            self.dir_modified =
+                dir_modified.filter(|m| m.elapsed().is_ok_and(|age| age > TIMESTAMP_RESOLUTION));
+            let paths: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect();
+            self.scripts.retain(|path, _| {
+                let keep = paths.contains(path);
+                if !keep {
+                    println!("unloaded script {}", path.display());
+                }
+                keep
+            });
+            paths
+        };
+        for path in paths {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            if self
+                .scripts
+                .get(&path)
+                .is_some_and(|script| script.modified == modified)
+            {
+                continue;
+            }
+            let script = self.load(&path, modified);
+            if let Some(e) = &script.last_error {
+                errors.push(e.clone());
+            } else {
+                println!("loaded script {}", path.display());
+            }
+            self.scripts.insert(path, script);
+        }
+        errors
+    }
+
+    /// Compile a script and run its top level statements
+    fn load(&self, path: &Path, modified: Option<SystemTime>) -> Script {
+        let mut script = Script {
+            modified,
+            ast: None,
+            scope: Scope::new(),
+            state: Map::new().into(),
+            last_error: None,
+        };
+        let result = self.engine.compile_file(path.into()).and_then(|ast| {
+            self.engine.run_ast_with_scope(&mut script.scope, &ast)?;
+            Ok(ast)
+        });
+        match result {
+            Ok(ast) => script.ast = Some(ast),
+            Err(e) => script.last_error = Some(format!("{}: {e}", path.display())),
+        }
+        script
+    }
+
+    /// Run the function for an event in every script that defines it, returning the requested
+    /// actions and any new errors. A script that fails keeps running on later events.
+    pub fn run(&mut self, event: Event, input: ScriptInput) -> (Vec<ScriptAction>, Vec<String>) {
+        *self.input.borrow_mut() = input;
+        let name = event.function();
+        let mut errors = Vec::new();
+        for (path, script) in &mut self.scripts {
+            let Some(ast) = &script.ast else {
+                continue;
+            };
+            if !ast
+                .iter_functions()
+                .any(|f| f.name == name && f.params.is_empty())
+            {
+                continue;
+            }
+            let options = CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut script.state);
+            let result = self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut script.scope,
+                ast,
+                name,
+                (),
+            );
+            match result {
+                Ok(_) => script.last_error = None,
+                Err(e) => {
+                    let error = format!("{}: {e}", path.display());
+                    if script.last_error.as_ref() != Some(&error) {
+                        errors.push(error.clone());
+                    }
+                    script.last_error = Some(error);
+                },
+            }
+        }
+        (std::mem::take(&mut *self.actions.borrow_mut()), errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, source: &str, age: u64) {
+        let path = dir.join(name);
+        std::fs::write(&path, source).unwrap();
+        // distinct modification times, since writes can land within the timestamp resolution
+        let modified = SystemTime::now() - Duration::from_secs(age);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn scripts() {
+        let dir = std::env::temp_dir().join(format!("zoom-sync-scripts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write(
+            &dir,
+            "dashboard.rhai",
+            r#"
+            const LIMIT = 80;
+            fn on_tick() {
+                this.ticks = (this.ticks ?? 0) + 1;
+                if metric("cpu") > LIMIT {
+                    set_screen("cpu");
+                    notify(`hot after ${this.ticks} ticks`);
+                }
+                set_system_info(metric("cpu"), 40, 1.5);
+            }
+            fn on_connect() { set_time("13:15"); }
+            "#,
+            10,
+        );
+        write(&dir, "broken.rhai", "fn on_tick() { loop {} }", 10);
+
+        let mut scripts = Scripts::new(dir.clone(), &ScriptsConfig::default());
+        assert!(scripts.reload().is_empty());
+        let input = |cpu| ScriptInput {
+            metrics: [(Field::Cpu, cpu)].into(),
+            ..Default::default()
+        };
+
+        // runaway scripts are stopped and reported once, without affecting the others
+        let (actions, errors) = scripts.run(Event::Tick, input(70.));
+        let info = |cpu| ScriptAction::SetSystemInfo {
+            cpu,
+            gpu: 40.,
+            download: 1.5,
+        };
+        assert_eq!(actions, [info(70.)]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("broken.rhai"), "{}", errors[0]);
+        let (actions, errors) = scripts.run(Event::Tick, input(90.));
+        assert_eq!(
+            actions,
+            [
+                ScriptAction::SetScreen("cpu".into()),
+                ScriptAction::Notify("hot after 2 ticks".into()),
+                info(90.)
+            ]
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            scripts.run(Event::Connect, input(0.)).0,
+            [ScriptAction::SetTime(Some("13:15".parse().unwrap()))]
+        );
+
+        // changes are picked up, and compile errors reported
+        write(&dir, "broken.rhai", "fn on_tick() {", 5);
+        let errors = scripts.reload();
+        assert_eq!(errors.len(), 1);
+        write(
+            &dir,
+            "broken.rhai",
+            "fn on_weather() { set_screen(\"weather\") }",
+            0,
+        );
+        assert!(scripts.reload().is_empty());
+        assert_eq!(
+            scripts.run(Event::Weather, input(0.)).0,
+            [ScriptAction::SetScreen("weather".into())]
+        );
+        std::fs::remove_file(dir.join("broken.rhai")).unwrap();
+        scripts.reload();
+        assert!(scripts.run(Event::Weather, input(0.)).0.is_empty());
+
+        // no access to files, and only media in the scripts directory
+        write(&dir, "sneaky.rhai", r#"import "/etc/passwd" as p;"#, 0);
+        assert_eq!(scripts.reload().len(), 1);
+        write(&dir, "hot.png", "", 0);
+        write(
+            &dir,
+            "media.rhai",
+            r#"fn on_weather() { upload_image("hot.png"); upload_gif("../../etc/passwd"); }"#,
+            0,
+        );
+        scripts.reload();
+        let (actions, errors) = scripts.run(Event::Weather, input(0.));
+        assert_eq!(
+            actions,
+            [ScriptAction::UploadImage(
+                dir.join("hot.png").canonicalize().unwrap()
+            )]
+        );
+        assert!(
+            errors[0].contains("outside the scripts directory"),
+            "{}",
+            errors[0]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
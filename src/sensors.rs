@@ -0,0 +1,357 @@
+//! Precisely addressed temperature sensors from hwmon, thermal zones, or raw sysfs files
+
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use sysinfo::Components;
+
+use crate::hwmon::{self, HWMON_ROOT};
+
+/// Default thermal zone sysfs root
+pub const THERMAL_ROOT: &str = "/sys/class/thermal";
+
+/// How to combine readings when a sensor spec matches multiple inputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Hottest matching sensor
+    #[default]
+    Max,
+    /// Average over all matching sensors
+    Avg,
+}
+
+/// Which sensors to read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SensorQuery {
+    /// Hwmon chip name and optional input label (or `tempN` input name), both allowing `*`
+    Hwmon { chip: String, label: Option<String> },
+    /// Thermal zone type, allowing `*`
+    Thermal(String),
+    /// Raw sysfs file reporting millidegrees celsius
+    File(PathBuf),
+}
+
+/// A temperature sensor spec, in the form `[max:|avg:]hwmon:CHIP[:LABEL]`,
+/// `[max:|avg:]thermal:TYPE`, or `file:PATH`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SensorSpec {
+    pub aggregate: Aggregate,
+    pub query: SensorQuery,
+}
+
+impl FromStr for SensorSpec {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (aggregate, rest) = match s.split_once(':') {
+            Some(("max", rest)) => (Aggregate::Max, rest),
+            Some(("avg", rest)) => (Aggregate::Avg, rest),
+            _ => (Aggregate::default(), s),
+        };
+        let query = match rest.split_once(':') {
+            Some(("hwmon", rest)) if !rest.is_empty() => match rest.split_once(':') {
+                Some((chip, label)) => SensorQuery::Hwmon {
+                    chip: chip.into(),
+                    label: Some(label.into()),
+                },
+                None => SensorQuery::Hwmon {
+                    chip: rest.into(),
+                    label: None,
+                },
+            },
+            Some(("thermal", zone)) if !zone.is_empty() => SensorQuery::Thermal(zone.into()),
+            Some(("file", path)) if !path.is_empty() => SensorQuery::File(path.into()),
+            _ => {
+                return Err(format!(
+                    "invalid sensor '{s}', must be one of: hwmon:CHIP[:LABEL], thermal:TYPE, \
+                     file:PATH"
+                ))
+            },
+        };
+        Ok(Self { aggregate, query })
+    }
+}
+
+impl Display for SensorSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.aggregate == Aggregate::Avg {
+            f.write_str("avg:")?;
+        }
+        match &self.query {
+            SensorQuery::Hwmon { chip, label: None } => write!(f, "hwmon:{chip}"),
+            SensorQuery::Hwmon {
+                chip,
+                label: Some(label),
+            } => write!(f, "hwmon:{chip}:{label}"),
+            SensorQuery::Thermal(zone) => write!(f, "thermal:{zone}"),
+            SensorQuery::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+impl SensorSpec {
+    /// Find all sensor files matching this spec
+    pub fn resolve(&self, hwmon_root: &Path, thermal_root: &Path) -> Vec<PathBuf> {
+        match &self.query {
+            SensorQuery::Hwmon { chip, label } => hwmon::chips(hwmon_root)
+                .into_iter()
+                .filter(|c| glob_match(chip, &c.name))
+                .flat_map(|c| c.temps())
+                .filter(|t| match label {
+                    Some(pattern) => {
+                        t.label.as_deref().is_some_and(|l| glob_match(pattern, l))
+                            || glob_match(pattern, &input_name(&t.path))
+                    },
+                    None => true,
+                })
+                .map(|t| t.path)
+                .collect(),
+            SensorQuery::Thermal(zone) => thermal_zones(thermal_root)
+                .into_iter()
+                .filter(|(kind, _)| glob_match(zone, kind))
+                .map(|(_, path)| path)
+                .collect(),
+            SensorQuery::File(path) => vec![path.clone()],
+        }
+    }
+}
+
+/// Helper struct to track a temperature from one or more sysfs sensor files
+pub struct TempSensor {
+    aggregate: Aggregate,
+    paths: Vec<PathBuf>,
+}
+
+impl TempSensor {
+    /// Resolve a sensor spec against the system sysfs roots
+    pub fn new(spec: &SensorSpec) -> Self {
+        Self::with_roots(spec, Path::new(HWMON_ROOT), Path::new(THERMAL_ROOT))
+    }
+
+    /// Resolve a sensor spec against specific hwmon and thermal zone roots
+    pub fn with_roots(spec: &SensorSpec, hwmon_root: &Path, thermal_root: &Path) -> Self {
+        let paths = spec.resolve(hwmon_root, thermal_root);
+        if paths.is_empty() {
+            eprintln!("warning: no sensors found for {spec} (see `zoom-sync sensors`)");
+        }
+        Self {
+            aggregate: spec.aggregate,
+            paths,
+        }
+    }
+
+    /// Read and aggregate the current temperature in celsius, skipping unreadable sensors
+    pub fn read(&self) -> Option<f32> {
+        let temps: Vec<_> = self
+            .paths
+            .iter()
+            .filter_map(|p| hwmon::read_temp(p))
+            .collect();
+        if temps.is_empty() {
+            return None;
+        }
+        Some(match self.aggregate {
+            Aggregate::Max => temps.iter().copied().fold(f32::MIN, f32::max),
+            Aggregate::Avg => temps.iter().sum::<f32>() / temps.len() as f32,
+        })
+    }
+}
+
+/// List all thermal zones under a root directory as `(type, temp file)`
+pub fn thermal_zones(root: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut zones: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            let index: u32 = name.strip_prefix("thermal_zone")?.parse().ok()?;
+            let kind = fs::read_to_string(path.join("type")).ok()?;
+            Some((index, kind.trim().to_string(), path.join("temp")))
+        })
+        .collect();
+    zones.sort_by_key(|(i, ..)| *i);
+    zones.into_iter().map(|(_, k, p)| (k, p)).collect()
+}
+
+/// Print every discoverable sensor with its current reading and config string
+pub fn print_sensors() {
+    let hwmon_root = Path::new(HWMON_ROOT);
+    let thermal_root = Path::new(THERMAL_ROOT);
+    let mut found = false;
+
+    for chip in hwmon::chips(hwmon_root) {
+        for temp in chip.temps() {
+            let label = temp.label.clone().unwrap_or_else(|| input_name(&temp.path));
+            let spec = SensorSpec {
+                aggregate: Aggregate::Max,
+                query: SensorQuery::Hwmon {
+                    chip: chip.name.clone(),
+                    label: Some(label),
+                },
+            };
+            print_sensor(&spec, &temp.path, hwmon_root, thermal_root);
+            found = true;
+        }
+    }
+
+    for (kind, path) in thermal_zones(thermal_root) {
+        let spec = SensorSpec {
+            aggregate: Aggregate::Max,
+            query: SensorQuery::Thermal(kind),
+        };
+        print_sensor(&spec, &path, hwmon_root, thermal_root);
+        found = true;
+    }
+
+    // Fall back to listing sysinfo component labels on platforms without sysfs
+    if !found {
+        let comps = Components::new_with_refreshed_list();
+        for c in comps.list() {
+            let temp = c
+                .temperature()
+                .map(|t| format!("{t:.1}°C"))
+                .unwrap_or("-".into());
+            println!("{:<40} {temp:>8}", c.label());
+            found = true;
+        }
+    }
+
+    if !found {
+        eprintln!("no temperature sensors found");
+    }
+}
+
+fn print_sensor(spec: &SensorSpec, path: &Path, hwmon_root: &Path, thermal_root: &Path) {
+    let temp = hwmon::read_temp(path)
+        .map(|t| format!("{t:.1}°C"))
+        .unwrap_or("-".into());
+    // Specs matching multiple sensors report the hottest, so show the exact path as well
+    if spec.resolve(hwmon_root, thermal_root).len() > 1 {
+        println!(
+            "{:<40} {temp:>8}  (or file:{})",
+            spec.to_string(),
+            path.display()
+        );
+    } else {
+        println!("{:<40} {temp:>8}", spec.to_string());
+    }
+}
+
+/// Input name for a hwmon temperature file, ie `temp1` for `temp1_input`
+fn input_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_suffix("_input"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Case insensitive matching with `*` wildcards
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hwmon::tests::fake_hwmon;
+
+    #[test]
+    fn parse_spec() {
+        for s in [
+            "hwmon:nvme",
+            "hwmon:coretemp:Core *",
+            "avg:hwmon:coretemp:Core*",
+            "thermal:x86_pkg_temp",
+            "file:/sys/class/hwmon/hwmon1/temp1_input",
+        ] {
+            let spec: SensorSpec = s.parse().unwrap();
+            assert_eq!(spec.to_string(), s);
+        }
+        assert_eq!(
+            "max:thermal:acpitz"
+                .parse::<SensorSpec>()
+                .unwrap()
+                .to_string(),
+            "thermal:acpitz"
+        );
+        assert!("Package".parse::<SensorSpec>().is_err());
+        assert!("hwmon:".parse::<SensorSpec>().is_err());
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("Core*", "Core 0"));
+        assert!(glob_match("*pkg*", "x86_pkg_temp"));
+        assert!(glob_match("nvme", "NVME"));
+        assert!(!glob_match("nvme", "nvme1"));
+        assert!(!glob_match("a*a", "a"));
+    }
+
+    #[test]
+    fn aggregate() {
+        let hwmon = fake_hwmon(
+            "sensors-hwmon",
+            &[
+                (
+                    "coretemp",
+                    &[
+                        (Some("Package id 0"), 60000),
+                        (Some("Core 0"), 50000),
+                        (Some("Core 1"), 56000),
+                    ],
+                ),
+                ("nvme", &[(Some("Composite"), 38000)]),
+                ("nvme", &[(Some("Composite"), 44000)]),
+                ("radeon", &[(None, 40000)]),
+            ],
+        );
+        let thermal =
+            std::env::temp_dir().join(format!("zoom-sync-sensors-thermal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&thermal);
+        for (i, (kind, temp)) in [("acpitz", 30000), ("x86_pkg_temp", 61000)]
+            .iter()
+            .enumerate()
+        {
+            let zone = thermal.join(format!("thermal_zone{i}"));
+            fs::create_dir_all(&zone).unwrap();
+            fs::write(zone.join("type"), format!("{kind}\n")).unwrap();
+            fs::write(zone.join("temp"), format!("{temp}\n")).unwrap();
+        }
+
+        let read =
+            |spec: &str| TempSensor::with_roots(&spec.parse().unwrap(), &hwmon, &thermal).read();
+        assert_eq!(read("hwmon:nvme"), Some(44.));
+        assert_eq!(read("avg:hwmon:nvme:Composite"), Some(41.));
+        assert_eq!(read("hwmon:coretemp:Core*"), Some(56.));
+        assert_eq!(read("avg:hwmon:coretemp:core*"), Some(53.));
+        assert_eq!(read("hwmon:radeon:temp1"), Some(40.));
+        assert_eq!(read("thermal:x86_pkg_temp"), Some(61.));
+        let file = format!("file:{}", thermal.join("thermal_zone0/temp").display());
+        assert_eq!(read(&file), Some(30.));
+        assert_eq!(read("hwmon:k10temp"), None);
+
+        fs::remove_dir_all(hwmon).unwrap();
+        fs::remove_dir_all(thermal).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+//! Command output source
+
+use std::time::Duration;
+
+use tokio::process::Command;
+
+/// Run a command through the platform shell, returning stdout
+pub async fn run(command: &str, timeout: Duration) -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.stdin(std::process::Stdio::null()).kill_on_drop(true);
+
+    let output = tokio::time::timeout(timeout, cmd.output())
+        .await
+        .map_err(|_| {
+            format!(
+                "`{command}` timed out after {}",
+                humantime::format_duration(timeout)
+            )
+        })?
+        .map_err(|e| format!("failed to run `{command}`: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("`{command}` exited with {}", output.status));
+    }
+    String::from_utf8(output.stdout).map_err(|e| format!("invalid output from `{command}`: {e}"))
+}
@@ -0,0 +1,31 @@
+//! HTTP json source
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::Client;
+
+/// Fetch a url with the given headers, returning the response body
+pub async fn get(
+    client: &Client,
+    url: &str,
+    headers: &BTreeMap<String, String>,
+    timeout: Duration,
+) -> Result<String, String> {
+    let mut request = client.get(url).timeout(timeout);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("failed to fetch {url}: {e}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("{url} returned {status}"));
+    }
+    response
+        .text()
+        .await
+        .map_err(|e| format!("invalid response from {url}: {e}"))
+}
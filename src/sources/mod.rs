@@ -0,0 +1,402 @@
+//! Custom data sources feeding display fields from user commands or http endpoints
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+
+use crate::temperature::{RangeConfig, RangePolicy};
+
+mod command;
+mod http;
+
+/// Display fields that can be fed by a custom source
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Cpu,
+    Gpu,
+    Download,
+    WeatherCurrent,
+    WeatherMin,
+    WeatherMax,
+}
+
+/// Where a source gets its data from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SourceKind {
+    /// Shell command, with stdout parsed as a number or json
+    Command { command: String },
+    /// HTTP GET request, with the response body parsed as a number or json
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceConfig {
+    #[serde(flatten)]
+    pub kind: SourceKind,
+    /// Path to the value when the output is json, either a JSON pointer (ie "/data/0/value")
+    /// or a dotted path (ie "data.0.value")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Maximum time to wait for a value
+    #[serde(default = "default_timeout", with = "humantime_serde")]
+    pub timeout: Duration,
+    /// Time between runs (0s runs on every system info refresh)
+    #[serde(default, with = "humantime_serde")]
+    pub interval: Duration,
+    /// Value to show when the source fails. Otherwise the last value is kept, or the built-in
+    /// data is shown if there never was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<f32>,
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Latest weather values from custom sources, overriding the fetched data
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WeatherOverrides {
+    pub current: Option<f32>,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl WeatherOverrides {
+    /// Check if no weather field has a value from a source
+    pub fn is_empty(&self) -> bool {
+        self.current.is_none() && self.min.is_none() && self.max.is_none()
+    }
+
+    /// Fit the values from sources into the displayable range, using the given values for the
+    /// fields without one
+    pub fn fit(
+        &self,
+        ranges: &RangeConfig,
+        fahrenheit: bool,
+        (current, min, max): (u8, u8, u8),
+    ) -> (u8, u8, u8) {
+        let fit = |policy: RangePolicy, name, value: Option<f32>, default| {
+            value.map_or(default, |v| policy.fit(name, v, fahrenheit).0)
+        };
+        (
+            fit(
+                ranges.weather_current,
+                "current weather",
+                self.current,
+                current,
+            ),
+            fit(ranges.weather_min, "minimum weather", self.min, min),
+            fit(ranges.weather_max, "maximum weather", self.max, max),
+        )
+    }
+}
+
+impl SourceConfig {
+    /// Run the source once, returning its value
+    async fn run(&self, client: &reqwest::Client) -> Result<f32, String> {
+        let output = match &self.kind {
+            SourceKind::Command { command } => command::run(command, self.timeout).await?,
+            SourceKind::Http { url, headers } => {
+                http::get(client, url, headers, self.timeout).await?
+            },
+        };
+        parse_output(&output, self.path.as_deref())
+    }
+}
+
+/// Poll a source on its interval, sending its value whenever it changes
+async fn poll(
+    field: Field,
+    config: SourceConfig,
+    client: reqwest::Client,
+    refresh: Duration,
+    tx: UnboundedSender<(Field, Option<f32>)>,
+) {
+    let period = if config.interval.is_zero() {
+        refresh
+    } else {
+        config.interval
+    };
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last = None;
+    loop {
+        interval.tick().await;
+        let value = match config.run(&client).await {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("warning: {field:?} source failed: {e}");
+                config.fallback.or(last)
+            },
+        };
+        if value != last {
+            last = value;
+            if tx.send((field, value)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Latest values of the configured sources, each polled on its own interval in the background
+/// so slow commands or requests never hold up the caller
+pub struct Sources {
+    values: BTreeMap<Field, f32>,
+    rx: UnboundedReceiver<(Field, Option<f32>)>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Sources {
+    /// Start polling the sources. Sources without an interval run every `refresh`.
+    pub fn new(config: &BTreeMap<Field, SourceConfig>, refresh: Duration) -> Self {
+        let (tx, rx) = unbounded_channel();
+        let client = reqwest::Client::new();
+        let tasks = config
+            .iter()
+            .map(|(field, config)| {
+                tokio::spawn(poll(
+                    *field,
+                    config.clone(),
+                    client.clone(),
+                    refresh,
+                    tx.clone(),
+                ))
+            })
+            .collect();
+        Self {
+            values: BTreeMap::new(),
+            rx,
+            tasks,
+        }
+    }
+
+    /// Wait for a source to change, returning the field it feeds. Never resolves without any
+    /// sources.
+    pub async fn changed(&mut self) -> Field {
+        // without any tasks left, nothing will change anymore
+        let Some((field, value)) = self.rx.recv().await else {
+            return std::future::pending().await;
+        };
+        match value {
+            Some(value) => self.values.insert(field, value),
+            None => self.values.remove(&field),
+        };
+        field
+    }
+
+    /// Get the latest value for a field, or `None` to use the built-in data
+    pub fn get(&self, field: Field) -> Option<f32> {
+        self.values.get(&field).copied()
+    }
+
+    /// Get the latest values for the weather fields
+    pub fn weather(&self) -> WeatherOverrides {
+        WeatherOverrides {
+            current: self.get(Field::WeatherCurrent),
+            min: self.get(Field::WeatherMin),
+            max: self.get(Field::WeatherMax),
+        }
+    }
+}
+
+impl Drop for Sources {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Field {
+    /// Check if the field is shown on the weather screen
+    pub fn is_weather(&self) -> bool {
+        matches!(
+            self,
+            Field::WeatherCurrent | Field::WeatherMin | Field::WeatherMax
+        )
+    }
+}
+
+/// Convert a dotted path into a JSON pointer, passing pointers through as-is
+fn to_pointer(path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        return path.to_string();
+    }
+    path.split('.')
+        .map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Parse source output as a plain number, or json with an optional path to the value
+pub fn parse_output(output: &str, path: Option<&str>) -> Result<f32, String> {
+    let output = output.trim();
+    if path.is_none() {
+        if let Ok(value) = output.parse() {
+            return Ok(value);
+        }
+    }
+
+    let json: Value =
+        serde_json::from_str(output).map_err(|e| format!("invalid number or json: {e}"))?;
+    let value = match path {
+        Some(path) => json
+            .pointer(&to_pointer(path))
+            .ok_or_else(|| format!("no value at {path}"))?,
+        None => &json,
+    };
+    match value {
+        Value::Number(n) => n.as_f64().map(|v| v as f32),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(b) => Some(*b as u8 as f32),
+        _ => None,
+    }
+    .ok_or_else(|| format!("value is not a number: {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weather::tests::http_stub;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_output("42\n", None), Ok(42.));
+        assert_eq!(parse_output(" 1.5 ", None), Ok(1.5));
+        assert_eq!(parse_output(r#""7""#, None), Ok(7.));
+        assert_eq!(
+            parse_output(r#"{"data":[{"value":"12.5"}]}"#, Some("/data/0/value")),
+            Ok(12.5)
+        );
+        assert_eq!(
+            parse_output(
+                r#"{"data":{"result":[{"value":[0,"3"]}]}}"#,
+                Some("data.result.0.value.1")
+            ),
+            Ok(3.)
+        );
+        assert!(parse_output(r#"{"a":1}"#, Some("/b")).is_err());
+        assert!(parse_output(r#"{"a":1}"#, None).is_err());
+        assert!(parse_output("error: not found", None).is_err());
+    }
+
+    /// Wait for source changes until no more arrive for a while
+    async fn settle(sources: &mut Sources, quiet: Duration) -> Vec<Field> {
+        let mut changed = Vec::new();
+        while let Ok(field) = tokio::time::timeout(quiet, sources.changed()).await {
+            changed.push(field);
+        }
+        changed
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn fallback_and_rate_limit() {
+        let config = |command: &str, interval, fallback| SourceConfig {
+            kind: SourceKind::Command {
+                command: command.into(),
+            },
+            path: None,
+            timeout: Duration::from_secs(3),
+            interval,
+            fallback,
+        };
+        // counts its runs, so a second run within the interval would change the value
+        let counter = std::env::temp_dir().join(format!("zoom-sync-source-{}", std::process::id()));
+        let _ = std::fs::remove_file(&counter);
+        let count = format!(
+            "n=$(( $(cat '{0}' 2>/dev/null || echo 0) + 1 )); echo $n > '{0}'; echo $n",
+            counter.display()
+        );
+        let refresh = Duration::from_millis(100);
+        let mut sources = Sources::new(
+            &BTreeMap::from([
+                (Field::Cpu, config("echo 42", Duration::ZERO, None)),
+                (
+                    Field::Gpu,
+                    config("sleep 60; echo 1", Duration::ZERO, Some(0.)),
+                ),
+                (Field::Download, config("false", Duration::ZERO, None)),
+                (
+                    Field::WeatherCurrent,
+                    config(&count, Duration::from_secs(60), None),
+                ),
+            ]),
+            refresh,
+        );
+
+        // the slow source only reports once it times out, so it doesn't hold up the others
+        assert_ne!(sources.changed().await, Field::Gpu);
+
+        let done = |sources: &Sources| {
+            sources.get(Field::Cpu).is_some()
+                && sources.get(Field::Gpu).is_some()
+                && sources.get(Field::WeatherCurrent).is_some()
+        };
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while !done(&sources) {
+                sources.changed().await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(sources.get(Field::Cpu), Some(42.));
+        // timed out, uses the fallback
+        assert_eq!(sources.get(Field::Gpu), Some(0.));
+        // failed without a fallback, uses built-in data
+        assert_eq!(sources.get(Field::Download), None);
+        assert_eq!(sources.get(Field::WeatherMin), None);
+        // polled once within its interval, while unchanged values aren't sent again
+        assert_eq!(sources.get(Field::WeatherCurrent), Some(1.));
+        assert!(settle(&mut sources, refresh * 5).await.is_empty());
+        let _ = std::fs::remove_file(&counter);
+    }
+
+    #[tokio::test]
+    async fn http() {
+        let url = http_stub(
+            &[("/api", r#"{"sensors":{"outdoor":{"temp":-3.5}}}"#)],
+            Some("authorization: bearer token"),
+        )
+        .await;
+
+        let config = |headers: &[(&str, &str)]| SourceConfig {
+            kind: SourceKind::Http {
+                url: format!("{url}/api"),
+                headers: headers
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            },
+            path: Some("sensors.outdoor.temp".into()),
+            timeout: Duration::from_secs(5),
+            interval: Duration::ZERO,
+            fallback: None,
+        };
+        let mut sources = Sources::new(
+            &BTreeMap::from([
+                (
+                    Field::WeatherCurrent,
+                    config(&[("Authorization", "Bearer token")]),
+                ),
+                (Field::WeatherMin, config(&[])),
+            ]),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(sources.changed().await, Field::WeatherCurrent);
+        let weather = sources.weather();
+        assert_eq!(weather.current, Some(-3.5));
+        // unauthorized, falls back to built-in data
+        assert_eq!(weather.min, None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Policies for fitting temperatures into the range the keyboard can display
+
+use serde::{Deserialize, Serialize};
+use zoom_sync_core::WeatherIcon;
+
+use crate::weather::IconName;
+
+/// Largest temperature the keyboard can display
+pub const MAX_DISPLAY: f32 = 99.;
+
+/// How to display a temperature outside of 0-99
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RangePolicy {
+    /// Clamp to the displayable range, so sub-zero temperatures show as 0
+    #[default]
+    Clamp,
+    /// Show the absolute value. Negative weather is marked with the negative icon, see
+    /// [`RangeConfig::negative_icon`].
+    Abs,
+    /// Add an offset before clamping, ie 40 to show -10 as 30
+    Offset(i16),
+    /// Show the temperature in the other unit when out of range, ie -10°C as 14°F
+    Switch,
+}
+
+impl RangePolicy {
+    /// Round a temperature and fit it into the displayable range, returning the value and if it
+    /// was negative and shown as the absolute value
+    pub fn apply(self, temp: f32, fahrenheit: bool) -> (u8, bool) {
+        let temp = temp.round();
+        let (temp, negated) = match self {
+            RangePolicy::Clamp => (temp, false),
+            RangePolicy::Abs => (temp.abs(), temp < 0.),
+            RangePolicy::Offset(offset) => (temp + offset as f32, false),
+            RangePolicy::Switch if !(0. ..=MAX_DISPLAY).contains(&temp) => {
+                let other = if fahrenheit {
+                    (temp - 32.) * 5. / 9.
+                } else {
+                    temp * 9. / 5. + 32.
+                };
+                (other.round(), false)
+            },
+            RangePolicy::Switch => (temp, false),
+        };
+        (temp.clamp(0., MAX_DISPLAY) as u8, negated)
+    }
+
+    /// Apply the policy, warning when the temperature didn't fit as-is
+    pub fn fit(self, name: &str, temp: f32, fahrenheit: bool) -> (u8, bool) {
+        let (value, negated) = self.apply(temp, fahrenheit);
+        if !(0. ..=MAX_DISPLAY).contains(&temp.round()) {
+            eprintln!("warning: {name} temperature at {temp}, showing {value} ({self:?})");
+        }
+        (value, negated)
+    }
+}
+
+/// Range policies for each temperature field
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RangeConfig {
+    pub cpu: RangePolicy,
+    pub gpu: RangePolicy,
+    pub weather_current: RangePolicy,
+    pub weather_min: RangePolicy,
+    pub weather_max: RangePolicy,
+    /// Replace the weather icon with `negative_icon` when a negative temperature is shown as its
+    /// absolute value. The actual condition isn't shown then, but without the marker -7 and 7
+    /// look the same.
+    pub mark_negative: bool,
+    /// Icon marking negative weather, ideally one the local weather rarely shows
+    pub negative_icon: IconName,
+}
+
+impl Default for RangeConfig {
+    fn default() -> Self {
+        Self {
+            cpu: RangePolicy::default(),
+            gpu: RangePolicy::default(),
+            weather_current: RangePolicy::default(),
+            weather_min: RangePolicy::default(),
+            weather_max: RangePolicy::default(),
+            mark_negative: true,
+            negative_icon: IconName(WeatherIcon::Snowfall),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies() {
+        // rounds instead of truncating
+        assert_eq!(RangePolicy::Clamp.apply(21.6, false), (22, false));
+        assert_eq!(RangePolicy::Clamp.apply(-7.2, false), (0, false));
+        assert_eq!(RangePolicy::Clamp.apply(104., false), (99, false));
+        assert_eq!(RangePolicy::Abs.apply(-7.2, false), (7, true));
+        assert_eq!(RangePolicy::Abs.apply(-0.3, false), (0, false));
+        assert_eq!(RangePolicy::Offset(40).apply(-10., false), (30, false));
+        assert_eq!(RangePolicy::Switch.apply(-10., false), (14, false));
+        assert_eq!(RangePolicy::Switch.apply(12., false), (12, false));
+        // 104F is 40C, and very cold readings still clamp
+        assert_eq!(RangePolicy::Switch.apply(104., true), (40, false));
+        assert_eq!(RangePolicy::Switch.apply(-30., false), (0, false));
+    }
+}
@@ -0,0 +1,180 @@
+//! Countdown and stopwatch timers, shown by driving the keyboard clock
+
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use zoom_sync_core::Board;
+
+/// Longest time that fits on the clock, 99:59
+const MAX_SECONDS: u64 = 99 * 60 + 59;
+
+/// Number of screen changes when a countdown finishes
+const FLASHES: usize = 6;
+
+/// Time between screen changes when a countdown finishes
+pub const FLASH_INTERVAL: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerMode {
+    Countdown(Duration),
+    Stopwatch,
+}
+
+impl FromStr for TimerMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("stopwatch") {
+            return Ok(Self::Stopwatch);
+        }
+        match humantime::parse_duration(s) {
+            Ok(duration) if !duration.is_zero() => Ok(Self::Countdown(duration)),
+            _ => Err(format!(
+                "invalid timer '{s}', must be a duration (ie 25m) or \"stopwatch\""
+            )),
+        }
+    }
+}
+
+impl Display for TimerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimerMode::Countdown(duration) => {
+                write!(f, "{} countdown", humantime::format_duration(*duration))
+            },
+            TimerMode::Stopwatch => f.write_str("stopwatch"),
+        }
+    }
+}
+
+/// Next update to show for a timer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerStep {
+    /// Minutes and seconds to show on the clock
+    Fields(u8, u8),
+    /// Screen to show while flashing a finished countdown, first when it just finished
+    Flash { screen: &'static str, first: bool },
+    /// The countdown finished and the flash is done
+    Done,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Timer {
+    pub mode: TimerMode,
+    started: Instant,
+    flashes: usize,
+}
+
+impl Timer {
+    pub fn new(mode: TimerMode) -> Self {
+        Self {
+            mode,
+            started: Instant::now(),
+            flashes: 0,
+        }
+    }
+
+    /// Advance the timer, flashing between the logo and time screens once a countdown has
+    /// finished and ending on the time screen
+    pub fn step(&mut self) -> TimerStep {
+        if let Some((minutes, seconds)) = self.fields() {
+            return TimerStep::Fields(minutes, seconds);
+        }
+        if self.flashes == FLASHES {
+            return TimerStep::Done;
+        }
+        let screen = if self.flashes % 2 == 0 {
+            "meletrix"
+        } else {
+            "time"
+        };
+        let first = self.flashes == 0;
+        self.flashes += 1;
+        TimerStep::Flash { screen, first }
+    }
+
+    /// Minutes and seconds to show in the hour and minute fields of the clock, or `None` once a
+    /// countdown has finished. The hour field has two digits, so minutes stop at 99:59.
+    pub fn fields(&self) -> Option<(u8, u8)> {
+        self.fields_at(Instant::now())
+    }
+
+    fn fields_at(&self, now: Instant) -> Option<(u8, u8)> {
+        let elapsed = now - self.started;
+        let seconds = match self.mode {
+            // round up, so a countdown starts on its full duration and ends on zero
+            TimerMode::Countdown(duration) => {
+                let remaining = duration.checked_sub(elapsed).filter(|d| !d.is_zero())?;
+                remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
+            },
+            TimerMode::Stopwatch => elapsed.as_secs(),
+        };
+        let seconds = seconds.min(MAX_SECONDS);
+        Some(((seconds / 60) as u8, (seconds % 60) as u8))
+    }
+}
+
+/// Show timer fields in place of the hour and minute on the keyboard clock
+pub fn apply_timer(
+    board: &mut dyn Board,
+    (minutes, seconds): (u8, u8),
+) -> Result<(), Box<dyn Error>> {
+    board
+        .as_time()
+        .ok_or("board does not support time")?
+        .set_clock_fields(minutes, seconds)?;
+    Ok(())
+}
+
+/// Show a notification for a finished timer
+pub fn notify_finished(mode: TimerMode) {
+    let _ = notify_rust::Notification::new()
+        .summary("zoom-sync: Timer finished")
+        .body(&format!("{mode} finished"))
+        .timeout(10000)
+        .show();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let pomodoro = Timer::new("25m".parse().unwrap());
+        let after = |secs: f32| pomodoro.started + Duration::from_secs_f32(secs);
+        assert_eq!(pomodoro.fields_at(after(0.)), Some((25, 0)));
+        assert_eq!(pomodoro.fields_at(after(0.5)), Some((25, 0)));
+        assert_eq!(pomodoro.fields_at(after(1.)), Some((24, 59)));
+        assert_eq!(pomodoro.fields_at(after(61.5)), Some((23, 59)));
+        assert_eq!(pomodoro.fields_at(after(1499.5)), Some((0, 1)));
+        assert_eq!(pomodoro.fields_at(after(1500.)), None);
+
+        let stopwatch = Timer::new("stopwatch".parse().unwrap());
+        let after = |secs| stopwatch.started + Duration::from_secs(secs);
+        assert_eq!(stopwatch.fields_at(after(75)), Some((1, 15)));
+        assert_eq!(stopwatch.fields_at(after(24 * 60 + 59)), Some((24, 59)));
+        assert_eq!(stopwatch.fields_at(after(25 * 60)), Some((25, 0)));
+        assert_eq!(
+            stopwatch.fields_at(after(2 * 3600 + 5 * 60)),
+            Some((99, 59))
+        );
+
+        assert!("0s".parse::<TimerMode>().is_err());
+        assert!("soon".parse::<TimerMode>().is_err());
+    }
+
+    #[test]
+    fn flash() {
+        let mut timer = Timer::new("1s".parse().unwrap());
+        timer.started -= Duration::from_secs(2);
+        let screens: Vec<_> = std::iter::from_fn(|| match timer.step() {
+            TimerStep::Flash { screen, .. } => Some(screen),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(screens, ["meletrix", "time"].repeat(FLASHES / 2));
+        assert_eq!(timer.step(), TimerStep::Done);
+    }
+}
@@ -1,6 +1,15 @@
 //! Command and state types for tray-daemon communication
 
-use crate::config::Config;
+use std::collections::BTreeMap;
+
+use chrono_tz::Tz;
+
+use super::rules::Revert;
+use crate::carousel::Carousel;
+use crate::config::{Config, FeatureToggles};
+use crate::rules::RuleState;
+use crate::schedule::Schedule;
+use crate::timer::{Timer, TimerMode};
 
 /// Commands sent from tray menu to the daemon
 #[derive(Debug, Clone)]
@@ -15,6 +24,8 @@ pub enum TrayCommand {
     Toggle12HrTime,
     /// Toggle fahrenheit/celsius
     ToggleFahrenheit,
+    /// Toggle cycling through the carousel screens
+    ToggleCarousel,
     /// Upload pre-encoded image data
     UploadImage(Vec<u8>),
     /// Upload pre-encoded GIF data
@@ -27,6 +38,12 @@ pub enum TrayCommand {
     ClearAllMedia,
     /// Reload config from file
     ReloadConfig,
+    /// Dismiss the active weather alert, reverting to the previous screen
+    DismissAlert,
+    /// Start a countdown or stopwatch on the clock screen
+    StartTimer(TimerMode),
+    /// Stop the running timer, restoring the real time
+    StopTimer,
     /// Quit the application
     Quit,
 }
@@ -58,4 +75,70 @@ pub struct TrayState {
     pub config: Config,
     /// Whether reactive mode is currently active (Linux only)
     pub reactive_active: bool,
+    /// Screen to revert to when the active weather alert ends
+    pub alert_restore: Option<String>,
+    /// Index of the world clock timezone currently shown
+    pub world_clock: usize,
+    /// Timer driving the clock, in place of the real time
+    pub timer: Option<Timer>,
+    /// Screen to revert to when the timer ends
+    pub timer_restore: Option<String>,
+    /// Position in the screen carousel
+    pub carousel: Carousel,
+    /// Applied schedule entry, and any manual change holding it off
+    pub schedule: Schedule,
+    /// Feature toggles set by the schedule, over the config
+    pub schedule_toggles: FeatureToggles,
+    /// Triggered rules, and conditions waiting out their delay
+    pub rules: RuleState,
+    /// Triggered rules by index, with the screen to restore once each clears. Their toggles
+    /// apply over the schedule.
+    pub triggered_rules: BTreeMap<usize, Revert>,
+}
+
+impl TrayState {
+    /// Timezone shown on the keyboard clock: the active world clock timezone, or the configured
+    /// timezone, defaulting to local time
+    pub fn clock_zone(&self) -> Option<Tz> {
+        let timezones = &self.config.world_clock.timezones;
+        if timezones.is_empty() {
+            self.config.general.timezone
+        } else {
+            Some(timezones[self.world_clock % timezones.len()])
+        }
+    }
+
+    /// Feature toggles set by triggered rules or the schedule, which take priority over the
+    /// config without being saved to it
+    fn overrides(&self) -> FeatureToggles {
+        let mut toggles = self.schedule_toggles;
+        // earlier rules take priority, like schedule entries
+        for i in self.triggered_rules.keys().rev() {
+            if let Some(rule) = self.config.rules.get(*i) {
+                toggles.merge(&rule.then.toggles);
+            }
+        }
+        toggles
+    }
+
+    /// Whether weather updates are on, after the schedule and rules
+    pub fn weather_enabled(&self) -> bool {
+        self.overrides()
+            .weather
+            .unwrap_or(self.config.weather.enabled)
+    }
+
+    /// Whether system info updates are on, after the schedule and rules
+    pub fn system_info_enabled(&self) -> bool {
+        self.overrides()
+            .system_info
+            .unwrap_or(self.config.system_info.enabled)
+    }
+
+    /// Whether the screen carousel is on, after the schedule and rules
+    pub fn carousel_enabled(&self) -> bool {
+        self.overrides()
+            .carousel
+            .unwrap_or(self.config.carousel.enabled)
+    }
 }
@@ -1,5 +1,7 @@
 //! Menu construction and event handling
 
+use std::time::Duration;
+
 use muda::{
     accelerator::Accelerator, AboutMetadata, CheckMenuItem, Menu, MenuEvent, MenuItem,
     PredefinedMenuItem, Submenu,
@@ -7,6 +9,7 @@ use muda::{
 use zoom_sync_core::Board;
 
 use super::commands::{TrayCommand, TrayState};
+use crate::timer::TimerMode;
 
 /// Menu item IDs for event handling
 pub mod ids {
@@ -31,6 +34,7 @@ pub mod ids {
     pub const TOGGLE_SYSTEM: &str = "toggle_system";
     pub const TOGGLE_12HR: &str = "toggle_12hr";
     pub const TOGGLE_FAHRENHEIT: &str = "toggle_fahrenheit";
+    pub const TOGGLE_CAROUSEL: &str = "toggle_carousel";
 
     // Media
     pub const UPLOAD_IMAGE: &str = "upload_image";
@@ -39,6 +43,13 @@ pub mod ids {
     pub const CLEAR_GIF: &str = "clear_gif";
     pub const CLEAR_ALL: &str = "clear_all";
 
+    // Timer
+    pub const TIMER_POMODORO: &str = "timer_pomodoro";
+    pub const TIMER_5M: &str = "timer_5m";
+    pub const TIMER_15M: &str = "timer_15m";
+    pub const TIMER_STOPWATCH: &str = "timer_stopwatch";
+    pub const TIMER_STOP: &str = "timer_stop";
+
     // Config
     pub const OPEN_CONFIG: &str = "open_config";
     pub const RELOAD_CONFIG: &str = "reload_config";
@@ -75,6 +86,7 @@ pub struct MenuItems {
     pub toggle_system: CheckMenuItem,
     pub toggle_12hr: CheckMenuItem,
     pub toggle_fahrenheit: CheckMenuItem,
+    pub toggle_carousel: CheckMenuItem,
 }
 
 impl MenuItems {
@@ -148,15 +160,14 @@ impl MenuItems {
         #[cfg(target_os = "linux")]
         self.screen_reactive.set_checked(reactive_active);
 
-        // Update toggles from config
-        self.toggle_weather
-            .set_checked(state.config.weather.enabled);
-        self.toggle_system
-            .set_checked(state.config.system_info.enabled);
+        // Update toggles from config and the schedule or rules
+        self.toggle_weather.set_checked(state.weather_enabled());
+        self.toggle_system.set_checked(state.system_info_enabled());
         self.toggle_12hr
             .set_checked(state.config.general.use_12hr_time);
         self.toggle_fahrenheit
             .set_checked(state.config.general.fahrenheit);
+        self.toggle_carousel.set_checked(state.carousel_enabled());
     }
 }
 
@@ -334,19 +345,44 @@ pub fn build_menu(state: &TrayState) -> MenuItems {
 
     menu.append(&PredefinedMenuItem::separator()).unwrap();
 
+    // Timer submenu
+    let timer_submenu = Submenu::new("Timer", true);
+    for (id, label) in [
+        (ids::TIMER_POMODORO, "Pomodoro (25 Minutes)"),
+        (ids::TIMER_5M, "5 Minutes"),
+        (ids::TIMER_15M, "15 Minutes"),
+        (ids::TIMER_STOPWATCH, "Stopwatch"),
+    ] {
+        timer_submenu
+            .append(&MenuItem::with_id(id, label, true, None::<Accelerator>))
+            .unwrap();
+    }
+    timer_submenu
+        .append(&PredefinedMenuItem::separator())
+        .unwrap();
+    timer_submenu
+        .append(&MenuItem::with_id(
+            ids::TIMER_STOP,
+            "Stop Timer",
+            true,
+            None::<Accelerator>,
+        ))
+        .unwrap();
+    menu.append(&timer_submenu).unwrap();
+
     // Settings toggles (inlined)
     let toggle_weather = CheckMenuItem::with_id(
         ids::TOGGLE_WEATHER,
         "Weather Updates",
         true,
-        state.config.weather.enabled,
+        state.weather_enabled(),
         None::<Accelerator>,
     );
     let toggle_system = CheckMenuItem::with_id(
         ids::TOGGLE_SYSTEM,
         "System Info Updates",
         true,
-        state.config.system_info.enabled,
+        state.system_info_enabled(),
         None::<Accelerator>,
     );
     let toggle_12hr = CheckMenuItem::with_id(
@@ -363,10 +399,18 @@ pub fn build_menu(state: &TrayState) -> MenuItems {
         state.config.general.fahrenheit,
         None::<Accelerator>,
     );
+    let toggle_carousel = CheckMenuItem::with_id(
+        ids::TOGGLE_CAROUSEL,
+        "Cycle Screens",
+        true,
+        state.carousel_enabled(),
+        None::<Accelerator>,
+    );
     menu.append(&toggle_weather).unwrap();
     menu.append(&toggle_system).unwrap();
     menu.append(&toggle_12hr).unwrap();
     menu.append(&toggle_fahrenheit).unwrap();
+    menu.append(&toggle_carousel).unwrap();
 
     menu.append(&PredefinedMenuItem::separator()).unwrap();
 
@@ -437,6 +481,7 @@ pub fn build_menu(state: &TrayState) -> MenuItems {
         toggle_system,
         toggle_12hr,
         toggle_fahrenheit,
+        toggle_carousel,
     }
 }
 
@@ -475,6 +520,7 @@ pub fn handle_menu_event(event: MenuEvent) -> MenuAction {
         ids::TOGGLE_SYSTEM => MenuAction::Command(TrayCommand::ToggleSystemInfo),
         ids::TOGGLE_12HR => MenuAction::Command(TrayCommand::Toggle12HrTime),
         ids::TOGGLE_FAHRENHEIT => MenuAction::Command(TrayCommand::ToggleFahrenheit),
+        ids::TOGGLE_CAROUSEL => MenuAction::Command(TrayCommand::ToggleCarousel),
 
         // Media - file dialogs need async handling
         ids::UPLOAD_IMAGE => MenuAction::PickImage,
@@ -483,6 +529,19 @@ pub fn handle_menu_event(event: MenuEvent) -> MenuAction {
         ids::CLEAR_GIF => MenuAction::Command(TrayCommand::ClearGif),
         ids::CLEAR_ALL => MenuAction::Command(TrayCommand::ClearAllMedia),
 
+        // Timer
+        ids::TIMER_POMODORO => MenuAction::Command(TrayCommand::StartTimer(TimerMode::Countdown(
+            Duration::from_secs(25 * 60),
+        ))),
+        ids::TIMER_5M => MenuAction::Command(TrayCommand::StartTimer(TimerMode::Countdown(
+            Duration::from_secs(5 * 60),
+        ))),
+        ids::TIMER_15M => MenuAction::Command(TrayCommand::StartTimer(TimerMode::Countdown(
+            Duration::from_secs(15 * 60),
+        ))),
+        ids::TIMER_STOPWATCH => MenuAction::Command(TrayCommand::StartTimer(TimerMode::Stopwatch)),
+        ids::TIMER_STOP => MenuAction::Command(TrayCommand::StopTimer),
+
         // Config
         ids::OPEN_CONFIG => {
             open_config_file();
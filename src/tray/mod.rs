@@ -1,15 +1,16 @@
 //! System tray interface for zoom-sync
 
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::io::{stdout, Seek, Write};
-use std::time::Duration;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::WindowsAndMessaging::{
     DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE,
 };
 
-use chrono::DurationRound;
 use either::Either;
 use futures::future::OptionFuture;
 use image::codecs::gif::GifDecoder;
@@ -22,16 +23,32 @@ use tokio_stream::StreamExt;
 use tray_icon::TrayIconBuilder;
 use zoom_sync_core::Board;
 
-use crate::config::Config;
+use crate::alerts::AlertState;
+use crate::carousel::{Carousel, CarouselStep};
+use crate::clock::{self, ClockWatch};
+use crate::config::{Config, FeatureToggles};
 use crate::detection::BoardKind;
 use crate::info::{apply_system, CpuTemp, GpuTemp};
 use crate::media::{encode_gif, encode_image};
-use crate::weather::apply_weather;
+use crate::network::NetworkRate;
+use crate::rules::{RuleState, Transition};
+use crate::schedule::{self, Schedule, ScheduleEntry};
+use crate::scripts::Event;
+use crate::sources::{Field, Sources};
+use crate::temperature::RangeConfig;
+use crate::timer::{self, Timer, TimerMode, TimerStep};
+use crate::weather::{WeatherRotation, WeatherUpdater};
+use sleep::SleepEvent;
 
 mod commands;
 mod menu;
+mod remote;
+mod rules;
+mod scripts;
+mod sleep;
 
 pub use commands::{ConnectionStatus, TrayCommand, TrayState};
+pub use remote::{send as send_timer, TimerRequest};
 
 /// Icon bytes embedded at compile time
 const ZOOM_ICON: &[u8] = include_bytes!("../../assets/zoom_icon.png");
@@ -78,6 +95,15 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
         current_screen: None,
         config,
         reactive_active: false,
+        alert_restore: None,
+        world_clock: 0,
+        timer: None,
+        timer_restore: None,
+        carousel: Carousel::default(),
+        schedule: Schedule::default(),
+        schedule_toggles: FeatureToggles::default(),
+        rules: RuleState::default(),
+        triggered_rules: BTreeMap::new(),
     };
 
     // Load icon and build menu
@@ -85,7 +111,7 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
     let menu_items = menu::build_menu(&state);
 
     // Create tray icon
-    let _tray = TrayIconBuilder::new()
+    let tray = TrayIconBuilder::new()
         .with_menu(Box::new(menu_items.menu.clone()))
         .with_tooltip("zoom-sync")
         .with_icon(icon)
@@ -113,6 +139,12 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
     // Internal command channel
     let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::unbounded_channel::<TrayCommand>();
 
+    // Timer commands from `zoom-sync timer`
+    #[cfg(unix)]
+    if let Err(e) = remote::listen(cmd_tx.clone()) {
+        eprintln!("warning: timer commands from the command line unavailable: {e}");
+    }
+
     // UI polling interval
     let mut ui_interval = tokio::time::interval(Duration::from_millis(200));
     ui_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
@@ -120,12 +152,15 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
     // Board connection state
     let mut board: Option<Box<dyn Board>> = None;
 
-    // Temperature monitors (initialized when board connects)
-    let mut cpu: Option<Either<CpuTemp, u8>> = None;
-    let mut gpu: Option<Either<GpuTemp, u8>> = None;
+    // System info monitors (initialized when board connects)
+    let mut monitors: Option<SystemMonitors> = None;
 
-    // Weather args
-    let mut weather_args = build_weather_args(&state.config);
+    // Weather updates, with the last fetch cached on disk
+    let mut weather = build_weather(&state.config, state.weather_enabled());
+    update_tooltip(&tray, &weather);
+
+    // Custom data sources for display fields
+    let mut sources = Sources::new(&state.config.sources, state.config.refresh.system);
 
     // Refresh intervals (skip missed ticks instead of bursting)
     let mut weather_interval = tokio::time::interval(state.config.refresh.weather);
@@ -134,9 +169,65 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
     system_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
     let mut retry_interval = tokio::time::interval(state.config.refresh.retry);
     retry_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut rotate_interval = create_rotate_interval(state.config.weather.rotate_interval);
+    let mut world_clock_interval = create_rotate_interval(state.config.world_clock.interval);
+
+    // Next step of the screen carousel
+    let mut carousel_step: Option<Pin<Box<tokio::time::Sleep>>> = state
+        .config
+        .carousel
+        .enabled
+        .then(|| Box::pin(tokio::time::sleep(Duration::ZERO)));
+
+    // Time-of-day schedule checks
+    let mut schedule_interval = tokio::time::interval(schedule::CHECK_INTERVAL);
+    schedule_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    // Rule evaluation, with the processes and idle time read when rules use them
+    let mut rules_interval = tokio::time::interval(state.config.refresh.rules);
+    rules_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut probe = rules::Probe::default();
+
+    // User scripts, reloaded when they change
+    let mut scripts_interval = tokio::time::interval(state.config.scripts.interval);
+    scripts_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut user_scripts = scripts::build(&state.config);
+
+    // Countdown or stopwatch updates
+    let mut timer_interval = tokio::time::interval(Duration::from_secs(1));
+    timer_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
-    // Time sync interval (only used in 12hr mode, syncs on the hour)
-    let mut time_interval: Option<tokio::time::Interval> = None;
+    // Next time sync, and checks for utc offset changes and clock jumps that need one sooner
+    let mut time_sync: Option<Pin<Box<tokio::time::Sleep>>> = None;
+    let mut clock_watch = ClockWatch::new(state.clock_zone());
+    let mut clock_check = tokio::time::interval(clock::CHECK_INTERVAL);
+    clock_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    // Sleep and resume, from logind or else from clock jumps. The board is reopened after a
+    // resume, restoring the screen it was on.
+    let (sleep_tx, mut sleep_rx) = tokio::sync::mpsc::unbounded_channel::<SleepEvent>();
+    let mut resume_from_jumps = !cfg!(target_os = "linux");
+    #[cfg(target_os = "linux")]
+    {
+        let tx = sleep_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = sleep::watch_logind(tx.clone()).await {
+                eprintln!(
+                    "warning: sleep signals unavailable, detecting resume from clock jumps: {e}"
+                );
+                let _ = tx.send(SleepEvent::Unavailable);
+            }
+        });
+    }
+    let mut last_resume: Option<Instant> = None;
+    let mut resume_screen: Option<String> = None;
+
+    // Re-push the weather icon at the next sunrise or sunset
+    let mut sun_transition = sun_timer(&weather);
+
+    // Severe weather alerts, reverting to the previous screen after a timeout
+    let mut alerts = AlertState::default();
+    let mut alert_revert: Option<Pin<Box<tokio::time::Sleep>>> = None;
 
     // Reactive mode (Linux only)
     #[cfg(target_os = "linux")]
@@ -189,23 +280,7 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                                         .await
                                     {
                                         let path = handle.path().to_path_buf();
-                                        // Encode in blocking thread
-                                        let result = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, ImageProcessingError> {
-                                            let image = image::open(&path)?;
-                                            encode_image(image, bg, nearest, width, height)
-                                                .ok_or(ImageProcessingError::EncodeImage)
-                                        }).await;
-                                        match result {
-                                            Ok(Ok(data)) => { let _ = tx.send(TrayCommand::UploadImage(data)); }
-                                            Ok(Err(e)) => {
-                                                eprintln!("{e}");
-                                                notify_error(&e.to_string());
-                                            }
-                                            Err(e) => {
-                                                eprintln!("image encoding task panicked: {e}");
-                                                notify_error(&format!("Image encoding failed: {e}"));
-                                            }
-                                        }
+                                        encode_and_send_image(path, (width, height), bg, nearest, tx).await;
                                     }
                                 });
                             } else {
@@ -227,21 +302,7 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                                         .await
                                     {
                                         let path = handle.path().to_path_buf();
-                                        // Decode and encode in blocking thread
-                                        let result = tokio::task::spawn_blocking(move || {
-                                            decode_and_encode_gif(&path, bg, nearest, width, height)
-                                        }).await;
-                                        match result {
-                                            Ok(Ok(data)) => { let _ = tx.send(TrayCommand::UploadGif(data)); }
-                                            Ok(Err(e)) => {
-                                                eprintln!("{e}");
-                                                notify_error(&e.to_string());
-                                            }
-                                            Err(e) => {
-                                                eprintln!("gif encoding task panicked: {e}");
-                                                notify_error(&format!("GIF encoding failed: {e}"));
-                                            }
-                                        }
+                                        encode_and_send_gif(path, (width, height), bg, nearest, tx).await;
                                     }
                                 });
                             } else {
@@ -260,12 +321,42 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                     &mut board,
                     &mut state,
                     &menu_items,
-                    &mut cpu,
-                    &mut gpu,
-                    &mut weather_args,
+                    &mut monitors,
+                    &mut weather,
+                    &mut sources,
                 ).await {
                     CommandResult::Quit => return Ok(()),
-                    CommandResult::Continue => {}
+                    // the weather may have been rebuilt or relocated
+                    CommandResult::Continue => {
+                        sun_transition = sun_timer(&weather);
+                        // 12hr time may have been toggled, changing the schedule
+                        if time_sync.is_some() {
+                            time_sync = time_timer(&state);
+                        }
+                        if state.alert_restore.is_none() {
+                            alert_revert = None;
+                        }
+                        update_tooltip(&tray, &weather);
+                        if rotate_interval.period() != state.config.weather.rotate_interval {
+                            rotate_interval = create_rotate_interval(state.config.weather.rotate_interval);
+                        }
+                        // the carousel may have been toggled
+                        sync_carousel(&state, &mut carousel_step);
+                        if world_clock_interval.period() != state.config.world_clock.interval {
+                            world_clock_interval = create_rotate_interval(state.config.world_clock.interval);
+                        }
+                        // scripts may have been enabled, disabled, or limited differently by a reload
+                        let scripts_config = &state.config.scripts;
+                        if user_scripts.as_ref().map(|s| s.max_operations())
+                            != scripts_config.enabled.then_some(scripts_config.max_operations)
+                        {
+                            user_scripts = scripts::build(&state.config);
+                        }
+                        if scripts_interval.period() != state.config.scripts.interval {
+                            scripts_interval = tokio::time::interval(state.config.scripts.interval);
+                            scripts_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                        }
+                    }
                     #[cfg(target_os = "linux")]
                     CommandResult::ToggleReactive => {
                         if state.reactive_active {
@@ -318,10 +409,9 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                         println!("connected to {}", b.info().name);
                         state.connection = ConnectionStatus::Connected;
 
-                        // Initialize temperature monitors
-                        if state.config.system_info.enabled {
-                            cpu = Some(Either::Left(CpuTemp::new(&state.config.system_info.cpu_source)));
-                            gpu = Some(Either::Left(GpuTemp::new(state.config.system_info.gpu_device)));
+                        // Initialize system info monitors
+                        if state.system_info_enabled() {
+                            monitors = Some(SystemMonitors::new(&state.config));
                         }
 
                         // Initialize reactive mode if configured (Linux only)
@@ -361,25 +451,35 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
 
                         if !skip_initial {
                             if let Some(screen) = b.as_screen() {
-                                let initial = &state.config.general.initial_screen;
-                                if screen.set_screen(initial).is_ok() {
-                                    state.current_screen = Some(initial.clone());
+                                let initial = resume_screen
+                                    .take()
+                                    .unwrap_or_else(|| state.config.general.initial_screen.clone());
+                                // a running timer keeps the time screen, restoring its own screen after
+                                let initial = if state.timer.is_some() { "time".to_string() } else { initial };
+                                if screen.set_screen(&initial).is_ok() {
+                                    state.current_screen = Some(initial);
                                 }
                             }
                         }
 
-                        // Sync time immediately
-                        if let Err(e) = crate::apply_time(b.as_mut(), state.config.general.use_12hr_time) {
-                            eprintln!("time sync failed: {e}");
-                        }
+                        // Sync time on the next second boundary
+                        let delay = clock::until_next_second(&clock::now(None));
+                        time_sync = Some(Box::pin(tokio::time::sleep(delay)));
 
-                        // Set up time interval for 12hr mode
-                        if state.config.general.use_12hr_time {
-                            time_interval = Some(create_hourly_interval());
+                        // Show cached weather until the next fetch succeeds
+                        if state.weather_enabled() {
+                            let overrides = sources.weather();
+                            if let Err(e) = weather.apply_cached(b.as_mut(), state.config.general.fahrenheit, &state.config.general.ranges, overrides) {
+                                eprintln!("weather update failed: {e}");
+                            }
                         }
+                        sun_transition = sun_timer(&weather);
 
                         // Set board, then update menu with features
                         board = Some(b);
+                        if let (Some(ref mut b), Some(s)) = (&mut board, &mut user_scripts) {
+                            scripts::run(s, Event::Connect, b.as_mut(), &mut state, &monitors, &weather, &cmd_tx);
+                        }
                         menu_items.update_from_state(&state, &mut board);
                     }
                     Err(e) => {
@@ -393,10 +493,20 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
             }
 
             // Weather updates (only if board connected and enabled)
-            _ = weather_interval.tick(), if board.is_some() && state.config.weather.enabled => {
+            _ = weather_interval.tick(), if board.is_some() && state.weather_enabled() => {
                 if let Some(ref mut b) = board {
-                    match apply_weather(b.as_mut(), &mut weather_args, state.config.general.fahrenheit).await {
-                        Ok(()) => {}
+                    let overrides = sources.weather();
+                    let result = weather.update(b.as_mut(), state.config.general.fahrenheit, &state.config.general.ranges, overrides).await;
+                    // the location may have been found or changed
+                    sun_transition = sun_timer(&weather);
+                    match result {
+                        Ok(None) => {
+                            if let Some(ref mut s) = user_scripts {
+                                scripts::run(s, Event::Weather, b.as_mut(), &mut state, &monitors, &weather, &cmd_tx);
+                            }
+                        }
+                        // back off instead of waiting for the next regular refresh
+                        Ok(Some(delay)) => weather_interval.reset_after(delay),
                         Err(e) => {
                             eprintln!("weather update failed: {e}");
                             // Check if board disconnected
@@ -406,19 +516,84 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                         }
                     }
                 }
+
+                // Raise alerts for newly severe weather
+                if state.config.alerts.enabled {
+                    let triggered = alerts.update(&state.config.alerts.rules, weather.data());
+                    if !triggered.is_empty() {
+                        if let Some(ref mut b) = board {
+                            start_alert(b.as_mut(), &mut state, &triggered, cmd_tx.clone());
+                        }
+                        alert_revert = Some(Box::pin(tokio::time::sleep(state.config.alerts.timeout)));
+                    }
+                }
+            }
+
+            // Revert the alert screen after the timeout
+            Some(_) = OptionFuture::from(alert_revert.as_mut()), if board.is_some() => {
+                alert_revert = None;
+                if let Some(ref mut b) = board {
+                    end_alert(b.as_mut(), &mut state);
+                }
+            }
+
+            // Show new weather values from custom sources right away
+            field = sources.changed() => {
+                if let (Some(ref mut b), true) = (&mut board, field.is_weather() && state.weather_enabled()) {
+                    if let Err(e) = weather.apply_cached(b.as_mut(), state.config.general.fahrenheit, &state.config.general.ranges, sources.weather()) {
+                        eprintln!("weather update failed: {e}");
+                        if e.to_string().contains("device") {
+                            handle_disconnect(&mut board, &mut state, &menu_items);
+                        }
+                    }
+                }
+            }
+
+            // Sunrise and sunset, flipping the day/night icon of the cached weather
+            Some(_) = OptionFuture::from(sun_transition.as_mut()), if board.is_some() && state.weather_enabled() => {
+                sun_transition = sun_timer(&weather);
+                if let Some(ref mut b) = board {
+                    let overrides = sources.weather();
+                    if let Err(e) = weather.apply_cached(b.as_mut(), state.config.general.fahrenheit, &state.config.general.ranges, overrides) {
+                        eprintln!("weather update failed: {e}");
+                        if e.to_string().contains("device") {
+                            handle_disconnect(&mut board, &mut state, &menu_items);
+                        }
+                    }
+                }
+            }
+
+            // Rotate between weather locations
+            _ = rotate_interval.tick(), if board.is_some() && state.weather_enabled() && weather.is_rotating() => {
+                if let Some(ref mut b) = board {
+                    let overrides = sources.weather();
+                    if let Err(e) = weather.rotate(b.as_mut(), state.config.general.fahrenheit, &state.config.general.ranges, overrides) {
+                        eprintln!("weather update failed: {e}");
+                        if e.to_string().contains("device") {
+                            handle_disconnect(&mut board, &mut state, &menu_items);
+                        }
+                    }
+                }
+                sun_transition = sun_timer(&weather);
+                update_tooltip(&tray, &weather);
+            }
+
+            // Rotate between world clock timezones, resyncing on the next second
+            _ = world_clock_interval.tick(), if board.is_some() && state.config.world_clock.timezones.len() > 1 => {
+                state.world_clock = (state.world_clock + 1) % state.config.world_clock.timezones.len();
+                if let Some(tz) = state.clock_zone() {
+                    println!("showing time for {tz}");
+                }
+                clock_watch = ClockWatch::new(state.clock_zone());
+                let delay = clock::until_next_second(&clock::now(None));
+                time_sync = Some(Box::pin(tokio::time::sleep(delay)));
             }
 
             // System info updates (only if board connected and enabled)
-            _ = system_interval.tick(), if board.is_some() && state.config.system_info.enabled => {
+            _ = system_interval.tick(), if board.is_some() && state.system_info_enabled() => {
                 if let Some(ref mut b) = board {
-                    if let (Some(ref mut c), Some(ref g)) = (&mut cpu, &gpu) {
-                        if let Err(e) = apply_system(
-                            b.as_mut(),
-                            state.config.general.fahrenheit,
-                            c,
-                            g,
-                            None,
-                        ) {
+                    if let Some(ref mut m) = monitors {
+                        if let Err(e) = m.apply(b.as_mut(), state.config.general.fahrenheit, &state.config.general.ranges, &sources) {
                             eprintln!("system update failed: {e}");
                             if e.to_string().contains("device") {
                                 handle_disconnect(&mut board, &mut state, &menu_items);
@@ -428,10 +603,12 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                 }
             }
 
-            // Time sync (12hr mode, on the hour)
-            Some(_) = OptionFuture::from(time_interval.as_mut().map(|i| i.tick())), if board.is_some() => {
-                if let Some(ref mut b) = board {
-                    if let Err(e) = crate::apply_time(b.as_mut(), state.config.general.use_12hr_time) {
+            // Time sync, on the hour in 12hr mode or periodically in 24hr mode
+            Some(_) = OptionFuture::from(time_sync.as_mut()), if board.is_some() => {
+                time_sync = time_timer(&state);
+                // the timer drives the clock while it runs
+                if let (Some(ref mut b), None) = (&mut board, state.timer) {
+                    if let Err(e) = crate::apply_time(b.as_mut(), state.clock_zone(), state.config.general.use_12hr_time) {
                         eprintln!("time sync failed: {e}");
                         if e.to_string().contains("device") {
                             handle_disconnect(&mut board, &mut state, &menu_items);
@@ -440,6 +617,133 @@ async fn async_tray_app(board_kind: BoardKind) -> Result<(), Box<dyn Error>> {
                 }
             }
 
+            // Cycle screens, waiting while reactive mode, an alert, or a timer owns the screen
+            Some(_) = OptionFuture::from(carousel_step.as_mut()), if board.is_some() => {
+                let busy = state.reactive_active || state.alert_restore.is_some() || state.timer.is_some();
+                let delay = match state.carousel.step(&state.config.carousel, Instant::now(), busy) {
+                    CarouselStep::Show(id, dwell) => {
+                        if let Some(screen) = board.as_mut().and_then(|b| b.as_screen()) {
+                            match screen.set_screen(&id) {
+                                Ok(()) => state.current_screen = Some(id),
+                                Err(e) => eprintln!("failed to set carousel screen: {e}"),
+                            }
+                        }
+                        dwell
+                    }
+                    CarouselStep::Wait(delay) => delay,
+                };
+                carousel_step = Some(Box::pin(tokio::time::sleep(delay)));
+            }
+
+            // Apply the schedule, unless a manual screen change holds it until the next boundary
+            _ = schedule_interval.tick(), if board.is_some() && !state.config.schedule.is_empty() => {
+                let now = clock::now(None).naive_local();
+                if let Some(entry) = state.schedule.update(&state.config.schedule, now).cloned() {
+                    println!("applying schedule for {entry}");
+                    set_toggles(&mut state, &mut weather, &mut monitors, |s| {
+                        s.schedule_toggles.merge(&entry.toggles)
+                    });
+                    if let Some(ref mut b) = board {
+                        apply_schedule(b.as_mut(), &mut state, &entry);
+                    }
+                    sun_transition = sun_timer(&weather);
+                    update_tooltip(&tray, &weather);
+                    sync_carousel(&state, &mut carousel_step);
+                    menu_items.update_from_state(&state, &mut board);
+                }
+            }
+
+            // Apply rules that triggered, and revert those that cleared
+            _ = rules_interval.tick(), if board.is_some() && !state.config.rules.is_empty() => {
+                let metrics = rules::metrics(&monitors, &weather, state.config.general.fahrenheit);
+                let snapshot = probe.snapshot(&state.config.rules, metrics).await;
+                let transitions = state.rules.update(&state.config.rules, &snapshot, Instant::now());
+                if let (Some(ref mut b), false) = (&mut board, transitions.is_empty()) {
+                    for transition in transitions {
+                        match transition {
+                            Transition::Trigger(i) => {
+                                let rule = state.config.rules[i].clone();
+                                println!("rule triggered: {}", rule.name);
+                                rules::trigger(i, &rule, b.as_mut(), &mut state, &mut weather, &mut monitors, &cmd_tx);
+                            }
+                            Transition::Revert(i) => {
+                                println!("rule cleared: {}", state.config.rules[i].name);
+                                rules::revert(i, b.as_mut(), &mut state, &mut weather, &mut monitors);
+                            }
+                        }
+                    }
+                    sun_transition = sun_timer(&weather);
+                    update_tooltip(&tray, &weather);
+                    sync_carousel(&state, &mut carousel_step);
+                    menu_items.update_from_state(&state, &mut board);
+                }
+            }
+
+            // Run user scripts on every tick
+            _ = scripts_interval.tick(), if board.is_some() && user_scripts.is_some() => {
+                if let (Some(ref mut b), Some(s)) = (&mut board, &mut user_scripts) {
+                    scripts::run(s, Event::Tick, b.as_mut(), &mut state, &monitors, &weather, &cmd_tx);
+                }
+            }
+
+            // Timer updates, every second while a timer runs and faster while it flashes
+            _ = timer_interval.tick(), if board.is_some() && state.timer.is_some() => {
+                if let Some(ref mut b) = board {
+                    match update_timer(b.as_mut(), &mut state) {
+                        Ok(Some(delay)) => timer_interval.reset_after(delay),
+                        Ok(None) => {},
+                        Err(e) => {
+                            eprintln!("timer update failed: {e}");
+                            if e.to_string().contains("device") {
+                                handle_disconnect(&mut board, &mut state, &menu_items);
+                            }
+                        },
+                    }
+                }
+            }
+
+            // Resync right away when the utc offset changes or the clock jumps
+            _ = clock_check.tick() => {
+                if let Some(change) = clock_watch.check(state.clock_zone()) {
+                    if resume_from_jumps && sleep::is_resume(&change) {
+                        let _ = sleep_tx.send(SleepEvent::Resumed);
+                    } else if board.is_some() {
+                        println!("{change}, resyncing time");
+                        let delay = clock::until_next_second(&clock::now(None));
+                        time_sync = Some(Box::pin(tokio::time::sleep(delay)));
+                    }
+                }
+            }
+
+            // Show the sleep screen before sleeping, and reopen the board after resuming, since
+            // the old handle is often dead. The reconnect resyncs the time, and the weather and
+            // system info are refreshed right after.
+            Some(event) = sleep_rx.recv() => match event {
+                SleepEvent::Suspending(done) => {
+                    println!("preparing for sleep");
+                    if let (Some(ref mut b), Some(id)) = (&mut board, &state.config.general.sleep_screen) {
+                        if let Some(Err(e)) = b.as_screen().map(|screen| screen.set_screen(id)) {
+                            eprintln!("failed to set sleep screen: {e}");
+                        }
+                    }
+                    let _ = done.send(());
+                }
+                SleepEvent::Resumed => {
+                    if last_resume.is_none_or(|t| t.elapsed() > sleep::RESUME_DEBOUNCE) {
+                        last_resume = Some(Instant::now());
+                        println!("resumed from sleep, reconnecting");
+                        if board.is_some() {
+                            resume_screen = state.current_screen.clone();
+                            handle_disconnect(&mut board, &mut state, &menu_items);
+                        }
+                        retry_interval.reset_immediately();
+                        weather_interval.reset_immediately();
+                        system_interval.reset_immediately();
+                    }
+                }
+                SleepEvent::Unavailable => resume_from_jumps = true,
+            },
+
             // Reactive mode keypress handling (Linux only)
             Some(Some(res)) = OptionFuture::from(reactive_stream.as_mut().map(|s| s.next())), if board.is_some() => {
                 match res {
@@ -488,9 +792,9 @@ async fn handle_command(
     board: &mut Option<Box<dyn Board>>,
     state: &mut TrayState,
     menu_items: &menu::MenuItems,
-    cpu: &mut Option<Either<CpuTemp, u8>>,
-    gpu: &mut Option<Either<GpuTemp, u8>>,
-    weather_args: &mut crate::weather::WeatherArgs,
+    monitors: &mut Option<SystemMonitors>,
+    weather: &mut WeatherRotation,
+    sources: &mut Sources,
 ) -> CommandResult {
     match cmd {
         TrayCommand::Quit => return CommandResult::Quit,
@@ -507,6 +811,15 @@ async fn handle_command(
                     match screen.set_screen(id) {
                         Ok(()) => {
                             state.current_screen = Some(id.to_string());
+                            // keep the user's choice over reverting an alert or timer, or cycling
+                            state.alert_restore = None;
+                            state.timer_restore = None;
+                            state
+                                .carousel
+                                .pause(Instant::now(), state.config.carousel.idle_timeout);
+                            state
+                                .schedule
+                                .hold(&state.config.schedule, clock::now(None).naive_local());
                             // Also save as default
                             state.config.general.initial_screen = id.to_string();
                             let _ = state.config.save();
@@ -520,35 +833,44 @@ async fn handle_command(
         },
 
         TrayCommand::ToggleWeather => {
-            state.config.weather.enabled = !state.config.weather.enabled;
-            *weather_args = build_weather_args(&state.config);
+            // the choice replaces any scheduled toggle, and is saved
+            state.config.weather.enabled = !state.weather_enabled();
+            state.schedule_toggles.weather = None;
+            *weather = build_weather(&state.config, state.weather_enabled());
             let _ = state.config.save();
             menu_items.update_from_state(state, board);
-            println!("weather: {}", state.config.weather.enabled);
+            println!("weather: {}", state.weather_enabled());
         },
         TrayCommand::ToggleSystemInfo => {
-            state.config.system_info.enabled = !state.config.system_info.enabled;
-            if state.config.system_info.enabled && board.is_some() {
-                *cpu = Some(Either::Left(CpuTemp::new(
-                    &state.config.system_info.cpu_source,
-                )));
-                *gpu = Some(Either::Left(GpuTemp::new(
-                    state.config.system_info.gpu_device,
-                )));
+            state.config.system_info.enabled = !state.system_info_enabled();
+            state.schedule_toggles.system_info = None;
+            if state.system_info_enabled() && board.is_some() {
+                *monitors = Some(SystemMonitors::new(&state.config));
             }
             let _ = state.config.save();
             menu_items.update_from_state(state, board);
-            println!("system info: {}", state.config.system_info.enabled);
+            println!("system info: {}", state.system_info_enabled());
         },
         TrayCommand::Toggle12HrTime => {
             state.config.general.use_12hr_time = !state.config.general.use_12hr_time;
-            if let Some(ref mut b) = board {
-                let _ = crate::apply_time(b.as_mut(), state.config.general.use_12hr_time);
+            if let (Some(ref mut b), None) = (&mut *board, state.timer) {
+                let _ = crate::apply_time(
+                    b.as_mut(),
+                    state.clock_zone(),
+                    state.config.general.use_12hr_time,
+                );
             }
             let _ = state.config.save();
             menu_items.update_from_state(state, board);
             println!("12hr time: {}", state.config.general.use_12hr_time);
         },
+        TrayCommand::ToggleCarousel => {
+            state.config.carousel.enabled = !state.carousel_enabled();
+            state.schedule_toggles.carousel = None;
+            let _ = state.config.save();
+            menu_items.update_from_state(state, board);
+            println!("carousel: {}", state.carousel_enabled());
+        },
         TrayCommand::ToggleFahrenheit => {
             state.config.general.fahrenheit = !state.config.general.fahrenheit;
             let _ = state.config.save();
@@ -557,19 +879,26 @@ async fn handle_command(
 
             // Immediately update displays with new temperature unit
             if let Some(ref mut b) = board {
-                if state.config.weather.enabled {
-                    if let Err(e) =
-                        apply_weather(b.as_mut(), weather_args, state.config.general.fahrenheit)
-                            .await
-                    {
+                if state.weather_enabled() {
+                    // re-push the cached weather rather than fetching again
+                    let overrides = sources.weather();
+                    if let Err(e) = weather.apply_cached(
+                        b.as_mut(),
+                        state.config.general.fahrenheit,
+                        &state.config.general.ranges,
+                        overrides,
+                    ) {
                         eprintln!("weather update failed: {e}");
                     }
                 }
-                if state.config.system_info.enabled {
-                    if let (Some(ref mut c), Some(ref g)) = (cpu, gpu) {
-                        if let Err(e) =
-                            apply_system(b.as_mut(), state.config.general.fahrenheit, c, g, None)
-                        {
+                if state.system_info_enabled() {
+                    if let Some(ref mut m) = monitors {
+                        if let Err(e) = m.apply(
+                            b.as_mut(),
+                            state.config.general.fahrenheit,
+                            &state.config.general.ranges,
+                            sources,
+                        ) {
                             eprintln!("system update failed: {e}");
                         }
                     }
@@ -658,14 +987,45 @@ async fn handle_command(
         },
 
         TrayCommand::ReloadConfig => {
+            // revert triggered rules, since the rules may have changed
+            if let Some(ref mut b) = board {
+                let triggered: Vec<usize> = state.triggered_rules.keys().copied().collect();
+                for i in triggered {
+                    rules::revert(i, b.as_mut(), state, weather, monitors);
+                }
+            }
+            state.rules = RuleState::default();
             if let Err(e) = state.config.reload() {
                 eprintln!("failed to reload config: {e}");
             } else {
                 println!("config reloaded");
-                *weather_args = build_weather_args(&state.config);
+                // reapply the active schedule entry, which may have changed
+                state.schedule = Schedule::default();
+                state.schedule_toggles = FeatureToggles::default();
+                *weather = build_weather(&state.config, state.weather_enabled());
+                *sources = Sources::new(&state.config.sources, state.config.refresh.system);
             }
             menu_items.update_from_state(state, board);
         },
+
+        TrayCommand::DismissAlert => {
+            if let Some(ref mut b) = board {
+                end_alert(b.as_mut(), state);
+            }
+        },
+        TrayCommand::StartTimer(mode) => start_timer(board.as_deref_mut(), state, mode),
+        TrayCommand::StopTimer => {
+            if let Some(timer) = state.timer {
+                println!("stopped {}", timer.mode);
+                match board {
+                    Some(b) => end_timer(b.as_mut(), state),
+                    None => {
+                        state.timer = None;
+                        state.timer_restore = None;
+                    },
+                }
+            }
+        },
     }
 
     CommandResult::Continue
@@ -681,38 +1041,182 @@ fn handle_disconnect(
     menu_items.update_from_state(state, board);
 }
 
-fn build_weather_args(config: &Config) -> crate::weather::WeatherArgs {
-    if config.weather.enabled {
-        if let (Some(lat), Some(lon)) = (config.weather.latitude, config.weather.longitude) {
-            crate::weather::WeatherArgs::Auto {
-                coords: Some(crate::weather::Coords {
-                    coords: (),
-                    lat: lat as f32,
-                    long: lon as f32,
-                }),
-            }
-        } else {
-            crate::weather::WeatherArgs::Auto { coords: None }
+/// Timer for the next sunrise or sunset at the weather location
+fn sun_timer(weather: &WeatherRotation) -> Option<Pin<Box<tokio::time::Sleep>>> {
+    weather
+        .until_sun_transition()
+        .map(|delay| Box::pin(tokio::time::sleep(delay)))
+}
+
+/// Show the active weather location in the tooltip, since the board can't display it
+fn update_tooltip(tray: &tray_icon::TrayIcon, weather: &WeatherRotation) {
+    let tooltip = match &weather.active().name {
+        Some(name) => format!("zoom-sync - weather: {name}"),
+        None => "zoom-sync".into(),
+    };
+    if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+        eprintln!("failed to set tooltip: {e}");
+    }
+}
+
+fn create_rotate_interval(period: Duration) -> tokio::time::Interval {
+    // skip the immediate first tick, the first location is shown when connecting
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    interval
+}
+
+/// Build the weather updates, rotating between the named locations if there are any
+fn build_weather(config: &Config, enabled: bool) -> WeatherRotation {
+    let updater = |name, latitude, longitude, city| {
+        WeatherUpdater::new(
+            name,
+            build_weather_args(config, enabled, latitude, longitude, city),
+            &config.weather,
+            config.refresh.weather,
+        )
+    };
+    let updaters = if config.weather.locations.is_empty() {
+        vec![updater(
+            None,
+            config.weather.latitude,
+            config.weather.longitude,
+            config.weather.location.clone(),
+        )]
+    } else {
+        config
+            .weather
+            .locations
+            .iter()
+            .map(|l| {
+                updater(
+                    Some(l.name.clone()),
+                    l.latitude,
+                    l.longitude,
+                    l.city.clone(),
+                )
+            })
+            .collect()
+    };
+    WeatherRotation::new(updaters)
+}
+
+fn build_weather_args(
+    config: &Config,
+    enabled: bool,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    city: Option<String>,
+) -> crate::weather::WeatherArgs {
+    if enabled {
+        let coords = match (latitude, longitude) {
+            (Some(lat), Some(lon)) => Some(crate::weather::Coords {
+                coords: (),
+                lat: lat as f32,
+                long: lon as f32,
+            }),
+            _ => None,
+        };
+        crate::weather::WeatherArgs::Auto {
+            coords,
+            city,
+            providers: Box::new(crate::weather::ProviderArgs {
+                provider: config.weather.provider,
+                fallback_provider: config.weather.fallback_provider,
+                api_key: config.weather.api_key.clone(),
+                base_url: config.weather.base_url.clone(),
+                geolocation_url: config.weather.geolocation_url.clone(),
+                geolocation_fallbacks: config.weather.geolocation_fallbacks.clone(),
+                ipinfo_token: config.weather.ipinfo_token.clone(),
+                ip_version: config.weather.ip_version,
+                ip_check_url: config.weather.ip_check_url.clone(),
+                geocoding_url: config.weather.geocoding_url.clone(),
+                proxy: config.weather.proxy.clone(),
+                timeout: config.weather.timeout.into(),
+                forecast: config.weather.forecast,
+                evening_hour: config.weather.evening_hour,
+                apparent: config.weather.apparent,
+            }),
         }
     } else {
         crate::weather::WeatherArgs::Disabled
     }
 }
 
-fn create_hourly_interval() -> tokio::time::Interval {
-    let now = chrono::Local::now();
-    let delay = now
-        .duration_trunc(chrono::TimeDelta::try_minutes(60).unwrap())
-        .unwrap()
-        .timestamp_millis()
-        + 100
-        - now.timestamp_millis();
-    let mut interval = tokio::time::interval_at(
-        tokio::time::Instant::now() + Duration::from_millis(delay as u64),
-        Duration::from_secs(60 * 60),
+/// System info monitors, built from the config when the board connects
+struct SystemMonitors {
+    cpu: Either<CpuTemp, f32>,
+    gpu: Either<GpuTemp, f32>,
+    net: Either<NetworkRate, f32>,
+    /// Values from the last update, for rules
+    values: BTreeMap<Field, f32>,
+}
+
+impl SystemMonitors {
+    fn new(config: &Config) -> Self {
+        Self {
+            cpu: Either::Left(CpuTemp::new(&config.system_info.cpu_source)),
+            gpu: Either::Left(GpuTemp::new(&config.system_info.gpu_device)),
+            net: Either::Left(NetworkRate::new(
+                config.system_info.network_interface.as_deref(),
+                config.system_info.network_direction,
+                config.system_info.network_unit,
+                config.system_info.network_auto_scale,
+            )),
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Update system info, preferring values from custom sources over the monitors
+    fn apply(
+        &mut self,
+        board: &mut dyn Board,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        sources: &Sources,
+    ) -> Result<(), Box<dyn Error>> {
+        let cpu = match sources.get(Field::Cpu) {
+            Some(value) => value,
+            None => self
+                .cpu
+                .as_mut()
+                .either(|c| c.get_temp(fahrenheit).unwrap_or_default(), |v| *v),
+        };
+        let gpu = match sources.get(Field::Gpu) {
+            Some(value) => value,
+            None => self
+                .gpu
+                .as_ref()
+                .either(|g| g.get_temp(fahrenheit).unwrap_or_default(), |v| *v),
+        };
+        let net = match sources.get(Field::Download) {
+            Some(value) => value,
+            None => self
+                .net
+                .as_mut()
+                .either(|n| n.get_rate().unwrap_or_default(), |v| *v),
+        };
+        self.values =
+            BTreeMap::from([(Field::Cpu, cpu), (Field::Gpu, gpu), (Field::Download, net)]);
+        apply_system(
+            board,
+            fahrenheit,
+            ranges,
+            &mut Either::Right(cpu),
+            &Either::Right(gpu),
+            &mut Either::Right(net),
+        )
+    }
+}
+
+/// Schedule the next regular time sync
+fn time_timer(state: &TrayState) -> Option<Pin<Box<tokio::time::Sleep>>> {
+    let delay = clock::until_sync(
+        &clock::now(state.clock_zone()),
+        state.config.general.use_12hr_time,
+        state.config.refresh.time,
     );
-    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-    interval
+    Some(Box::pin(tokio::time::sleep(delay)))
 }
 
 fn load_icon() -> Result<tray_icon::Icon, Box<dyn Error>> {
@@ -723,6 +1227,61 @@ fn load_icon() -> Result<tray_icon::Icon, Box<dyn Error>> {
     Ok(icon)
 }
 
+/// Encode an image file in a blocking thread, then send it to be uploaded
+async fn encode_and_send_image(
+    path: std::path::PathBuf,
+    (width, height): (u32, u32),
+    bg: [u8; 3],
+    nearest: bool,
+    tx: tokio::sync::mpsc::UnboundedSender<TrayCommand>,
+) {
+    let result = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, ImageProcessingError> {
+        let image = image::open(&path)?;
+        encode_image(image, bg, nearest, width, height).ok_or(ImageProcessingError::EncodeImage)
+    })
+    .await;
+    match result {
+        Ok(Ok(data)) => {
+            let _ = tx.send(TrayCommand::UploadImage(data));
+        },
+        Ok(Err(e)) => {
+            eprintln!("{e}");
+            notify_error(&e.to_string());
+        },
+        Err(e) => {
+            eprintln!("image encoding task panicked: {e}");
+            notify_error(&format!("Image encoding failed: {e}"));
+        },
+    }
+}
+
+/// Decode and encode an animation file in a blocking thread, then send it to be uploaded
+async fn encode_and_send_gif(
+    path: std::path::PathBuf,
+    (width, height): (u32, u32),
+    bg: [u8; 3],
+    nearest: bool,
+    tx: tokio::sync::mpsc::UnboundedSender<TrayCommand>,
+) {
+    let result = tokio::task::spawn_blocking(move || {
+        decode_and_encode_gif(&path, bg, nearest, width, height)
+    })
+    .await;
+    match result {
+        Ok(Ok(data)) => {
+            let _ = tx.send(TrayCommand::UploadGif(data));
+        },
+        Ok(Err(e)) => {
+            eprintln!("{e}");
+            notify_error(&e.to_string());
+        },
+        Err(e) => {
+            eprintln!("gif encoding task panicked: {e}");
+            notify_error(&format!("GIF encoding failed: {e}"));
+        },
+    }
+}
+
 /// Decode and encode a gif/animation file (runs in blocking thread)
 fn decode_and_encode_gif(
     path: &std::path::Path,
@@ -774,6 +1333,74 @@ fn parse_hex_color(hex: &str) -> Option<[u8; 3]> {
     Some([r, g, b])
 }
 
+/// Switch to the alert screen, remembering the current one, and notify about the alerts
+fn start_alert(
+    board: &mut dyn Board,
+    state: &mut TrayState,
+    alerts: &[String],
+    tx: tokio::sync::mpsc::UnboundedSender<TrayCommand>,
+) {
+    println!("weather alert: {}", alerts.join(", "));
+    if let Some(screen) = board.as_screen() {
+        if state.alert_restore.is_none() {
+            // a timer showing its time screen gets it back, and restores its own screen after
+            let timer_owned = state.timer.is_some() && state.timer_restore.is_some();
+            state.alert_restore = Some(if timer_owned {
+                "time".into()
+            } else {
+                state
+                    .current_screen
+                    .clone()
+                    .unwrap_or_else(|| state.config.general.initial_screen.clone())
+            });
+        }
+        if let Err(e) = screen.set_screen(&state.config.alerts.screen) {
+            eprintln!("failed to set alert screen: {e}");
+        }
+    }
+    notify_alert(alerts.join("\n"), state.config.alerts.timeout, tx);
+}
+
+/// Revert to the screen from before the alert, if it's still active
+fn end_alert(board: &mut dyn Board, state: &mut TrayState) {
+    let Some(id) = state.alert_restore.take() else {
+        return;
+    };
+    if let Some(screen) = board.as_screen() {
+        match screen.set_screen(&id) {
+            Ok(()) => println!("weather alert ended, reverted screen to {id}"),
+            Err(e) => eprintln!("failed to revert alert screen: {e}"),
+        }
+    }
+}
+
+/// Show a weather alert notification, dismissing the alert when it's closed
+fn notify_alert(
+    body: String,
+    timeout: Duration,
+    tx: tokio::sync::mpsc::UnboundedSender<TrayCommand>,
+) {
+    // waiting for the notification to close blocks, so do it off the runtime
+    std::thread::spawn(move || {
+        let notification = Notification::new()
+            .summary("zoom-sync: Weather alert")
+            .body(&body)
+            .action("default", "Dismiss")
+            .timeout(i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX))
+            .show();
+        match notification {
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Ok(handle) => handle.wait_for_action(|_| {
+                let _ = tx.send(TrayCommand::DismissAlert);
+            }),
+            // other platforms can't report acknowledgements, so rely on the timeout
+            #[cfg(not(all(unix, not(target_os = "macos"))))]
+            Ok(_) => drop(tx),
+            Err(e) => eprintln!("failed to show alert notification: {e}"),
+        }
+    });
+}
+
 /// Show an upload-in-progress notification
 fn notify_uploading(kind: &str) {
     let _ = Notification::new()
@@ -783,6 +1410,189 @@ fn notify_uploading(kind: &str) {
         .show();
 }
 
+/// Apply the theme and screen of a schedule entry. While an alert or timer owns the screen, the
+/// scheduled screen is shown once it ends instead.
+fn apply_schedule(board: &mut dyn Board, state: &mut TrayState, entry: &ScheduleEntry) {
+    let Some(screen) = board.as_screen() else {
+        return;
+    };
+    if let Some(theme) = &entry.theme {
+        if let Err(e) = screen.set_theme(theme) {
+            eprintln!("failed to set scheduled theme: {e}");
+        }
+    }
+    // changing the theme resets the screen, so restore it
+    let id = entry
+        .screen
+        .clone()
+        .or_else(|| entry.theme.as_ref().and(state.current_screen.clone()));
+    if let Some(id) = id {
+        show_screen(board, state, id);
+    }
+}
+
+/// Show a screen chosen by the schedule or a rule. Reactive mode keeps the screen, and while an
+/// alert or timer owns it, the screen is shown once they end instead. An alert during a timer
+/// reverts to the timer, so the timer restores the screen.
+fn show_screen(board: &mut dyn Board, state: &mut TrayState, id: String) {
+    if state.reactive_active {
+        return;
+    }
+    if let Some(restore) = state
+        .timer_restore
+        .as_mut()
+        .or(state.alert_restore.as_mut())
+    {
+        *restore = id;
+        return;
+    }
+    if let Some(screen) = board.as_screen() {
+        match screen.set_screen(&id) {
+            Ok(()) => state.current_screen = Some(id),
+            Err(e) => eprintln!("failed to set screen: {e}"),
+        }
+    }
+}
+
+/// Change the feature toggles set by the schedule or rules, rebuilding the weather updates and
+/// system monitors when they're turned on or off
+fn set_toggles(
+    state: &mut TrayState,
+    weather: &mut WeatherRotation,
+    monitors: &mut Option<SystemMonitors>,
+    change: impl FnOnce(&mut TrayState),
+) {
+    let weather_enabled = state.weather_enabled();
+    change(state);
+    if state.weather_enabled() != weather_enabled {
+        *weather = build_weather(&state.config, state.weather_enabled());
+    }
+    if state.system_info_enabled() && monitors.is_none() {
+        *monitors = Some(SystemMonitors::new(&state.config));
+    }
+}
+
+/// Start or stop stepping the carousel after it may have been toggled
+fn sync_carousel(state: &TrayState, step: &mut Option<Pin<Box<tokio::time::Sleep>>>) {
+    if !state.carousel_enabled() {
+        *step = None;
+    } else if step.is_none() {
+        *step = Some(Box::pin(tokio::time::sleep(Duration::ZERO)));
+    }
+}
+
+/// Start a timer on the time screen, remembering the screen to revert to when it ends. Without
+/// a board, the timer is shown once it connects.
+fn start_timer(board: Option<&mut dyn Board>, state: &mut TrayState, mode: TimerMode) {
+    let timer = Timer::new(mode);
+    state.timer = Some(timer);
+    println!("starting {mode}");
+    if state.timer_restore.is_none() {
+        state.timer_restore = Some(
+            state
+                .current_screen
+                .clone()
+                .unwrap_or_else(|| state.config.general.initial_screen.clone()),
+        );
+    }
+    let Some(board) = board else {
+        return;
+    };
+    if let Some(screen) = board.as_screen() {
+        match screen.set_screen("time") {
+            Ok(()) => state.current_screen = Some("time".into()),
+            Err(e) => eprintln!("failed to set timer screen: {e}"),
+        }
+    }
+    if let Some(fields) = timer.fields() {
+        if let Err(e) = timer::apply_timer(board, fields) {
+            eprintln!("timer update failed: {e}");
+        }
+    }
+}
+
+/// Show the running timer, or flash and end it once a countdown has finished. Returns the
+/// delay until the next update while flashing, which is shorter than the usual second.
+fn update_timer(
+    board: &mut dyn Board,
+    state: &mut TrayState,
+) -> Result<Option<Duration>, Box<dyn Error>> {
+    let Some(timer) = state.timer.as_mut() else {
+        return Ok(None);
+    };
+    match timer.step() {
+        TimerStep::Fields(minutes, seconds) => timer::apply_timer(board, (minutes, seconds))?,
+        TimerStep::Flash { screen: id, first } => {
+            if first {
+                println!("{} finished", timer.mode);
+                timer::notify_finished(timer.mode);
+            }
+            if let Some(screen) = board.as_screen() {
+                if let Err(e) = screen.set_screen(id) {
+                    eprintln!("failed to flash screen: {e}");
+                }
+            }
+            return Ok(Some(timer::FLASH_INTERVAL));
+        },
+        TimerStep::Done => end_timer(board, state),
+    }
+    Ok(None)
+}
+
+/// Restore the real time in the configured timezone and format, and the screen from before
+/// the timer
+fn end_timer(board: &mut dyn Board, state: &mut TrayState) {
+    state.timer = None;
+    if let Err(e) = crate::apply_time(
+        board,
+        state.clock_zone(),
+        state.config.general.use_12hr_time,
+    ) {
+        eprintln!("time sync failed: {e}");
+    }
+    let Some(id) = state.timer_restore.take() else {
+        return;
+    };
+    // an alert showing over the timer reverts to the timer's screen instead
+    if let Some(restore) = state.alert_restore.as_mut() {
+        *restore = id;
+        return;
+    }
+    if let Some(screen) = board.as_screen() {
+        match screen.set_screen(&id) {
+            Ok(()) => state.current_screen = Some(id),
+            Err(e) => eprintln!("failed to revert timer screen: {e}"),
+        }
+    }
+}
+
+/// Run a timer from the command line when no daemon is running, until a countdown finishes or
+/// ctrl-c, with the same handling as the tray, so the configured time format and initial screen
+/// are restored after
+pub async fn run_timer(board: &mut dyn Board, mode: TimerMode) -> Result<(), Box<dyn Error>> {
+    let mut state = TrayState {
+        config: Config::load().unwrap_or_default(),
+        ..Default::default()
+    };
+    println!("press ctrl-c to stop");
+    start_timer(Some(board), &mut state, mode);
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    while state.timer.is_some() {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Some(delay) = update_timer(board, &mut state)? {
+                    interval.reset_after(delay);
+                }
+            },
+            _ = tokio::signal::ctrl_c() => {
+                println!("stopped {mode}");
+                end_timer(board, &mut state);
+            },
+        }
+    }
+    Ok(())
+}
+
 /// Show a success notification
 fn notify_success(kind: &str) {
     let _ = Notification::new()
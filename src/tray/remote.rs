@@ -0,0 +1,141 @@
+//! Timer commands from the command line to a running daemon, over a unix socket
+
+use std::fmt::Display;
+use std::io;
+use std::str::FromStr;
+
+use super::TrayCommand;
+use crate::timer::TimerMode;
+
+/// Timer to start or stop, from `zoom-sync timer`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerRequest {
+    Start(TimerMode),
+    Stop,
+}
+
+impl FromStr for TimerRequest {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("stop") {
+            return Ok(Self::Stop);
+        }
+        s.parse().map(Self::Start)
+    }
+}
+
+impl Display for TimerRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimerRequest::Start(TimerMode::Countdown(duration)) => {
+                write!(f, "{}", humantime::format_duration(*duration))
+            },
+            TimerRequest::Start(TimerMode::Stopwatch) => f.write_str("stopwatch"),
+            TimerRequest::Stop => f.write_str("stop"),
+        }
+    }
+}
+
+impl From<TimerRequest> for TrayCommand {
+    fn from(request: TimerRequest) -> Self {
+        match request {
+            TimerRequest::Start(mode) => TrayCommand::StartTimer(mode),
+            TimerRequest::Stop => TrayCommand::StopTimer,
+        }
+    }
+}
+
+/// Get the socket path, next to the lock file
+#[cfg(unix)]
+fn path() -> Option<std::path::PathBuf> {
+    directories::ProjectDirs::from("", "", "zoom-sync")
+        .map(|dirs| dirs.config_dir().join("zoom-sync.sock"))
+}
+
+/// Send a timer request to the running daemon. Returns false when no daemon is listening.
+#[cfg(unix)]
+pub fn send(request: TimerRequest) -> io::Result<bool> {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    let Some(path) = path() else {
+        return Ok(false);
+    };
+    let mut stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        // a stale socket is left behind when the daemon didn't exit cleanly
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(false)
+        },
+        Err(e) => return Err(e),
+    };
+    writeln!(stream, "timer {request}")?;
+    Ok(true)
+}
+
+/// Sockets are only supported on unix, so the timer always runs standalone elsewhere
+#[cfg(not(unix))]
+pub fn send(_request: TimerRequest) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Listen for timer requests, forwarding them to the tray as commands. Must only be called
+/// while holding the daemon lock, since any existing socket is replaced.
+#[cfg(unix)]
+pub fn listen(tx: tokio::sync::mpsc::UnboundedSender<TrayCommand>) -> io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::UnixListener;
+
+    let path = path().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "could not determine socket path")
+    })?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("failed to accept timer request: {e}");
+                    continue;
+                },
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stream).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    match line.strip_prefix("timer ").map(TimerRequest::from_str) {
+                        Some(Ok(request)) => {
+                            let _ = tx.send(request.into());
+                        },
+                        _ => eprintln!("ignoring invalid request: {line}"),
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        for request in ["25m", "1h 30m", "stopwatch", "stop"] {
+            let parsed: TimerRequest = request.parse().unwrap();
+            assert_eq!(parsed.to_string(), request);
+        }
+        assert!("soon".parse::<TimerRequest>().is_err());
+    }
+}
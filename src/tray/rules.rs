@@ -0,0 +1,224 @@
+//! Applying and reverting rule actions, and reading the system state they react to
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use notify_rust::Notification;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::mpsc::UnboundedSender;
+use zoom_sync_core::Board;
+
+use super::commands::{TrayCommand, TrayState};
+use super::{encode_and_send_image, parse_hex_color, set_toggles, show_screen, SystemMonitors};
+use crate::rules::{process_name, Condition, Rule, Snapshot};
+use crate::sources::Field;
+use crate::weather::WeatherRotation;
+
+/// Screen shown by a triggered rule, restored when it clears. The rule's toggles apply for as
+/// long as it's triggered, so they need nothing to restore.
+#[derive(Debug, Clone, Default)]
+pub struct Revert {
+    /// Screen the rule showed, and the one to go back to
+    screen: Option<(String, String)>,
+}
+
+/// Apply the action of the rule at an index, marking it as triggered
+pub fn trigger(
+    index: usize,
+    rule: &Rule,
+    board: &mut dyn Board,
+    state: &mut TrayState,
+    weather: &mut WeatherRotation,
+    monitors: &mut Option<SystemMonitors>,
+    tx: &UnboundedSender<TrayCommand>,
+) {
+    let action = &rule.then;
+    if let Some(body) = &action.notify {
+        let _ = Notification::new()
+            .summary(&format!("zoom-sync: {}", rule.name))
+            .body(body)
+            .timeout(10000)
+            .show();
+    }
+    if let (Some(path), Some(size)) = (&action.image, board.as_screen_size()) {
+        let bg = parse_hex_color(&state.config.media.background_color).unwrap_or([0, 0, 0]);
+        let nearest = state.config.media.use_nearest_neighbor;
+        tokio::spawn(encode_and_send_image(
+            path.clone(),
+            size,
+            bg,
+            nearest,
+            tx.clone(),
+        ));
+    }
+
+    let id = action
+        .screen
+        .clone()
+        .or_else(|| action.image.as_ref().map(|_| "image".into()));
+    let screen = id.map(|id| {
+        let previous = state
+            .current_screen
+            .clone()
+            .unwrap_or_else(|| state.config.general.initial_screen.clone());
+        show_screen(board, state, id.clone());
+        (id, previous)
+    });
+    set_toggles(state, weather, monitors, |s| {
+        s.triggered_rules.insert(index, Revert { screen });
+    });
+}
+
+/// Clear the rule at an index, so the toggles follow the remaining triggered rules. The screen
+/// is only reverted if it wasn't changed since.
+pub fn revert(
+    index: usize,
+    board: &mut dyn Board,
+    state: &mut TrayState,
+    weather: &mut WeatherRotation,
+    monitors: &mut Option<SystemMonitors>,
+) {
+    let mut revert = None;
+    set_toggles(state, weather, monitors, |s| {
+        revert = s.triggered_rules.remove(&index)
+    });
+    if let Some((shown, previous)) = revert.and_then(|r| r.screen) {
+        if state.current_screen.as_ref() == Some(&shown) {
+            show_screen(board, state, previous);
+        }
+    }
+}
+
+/// Latest system info and weather values, in the units shown on the keyboard
+pub fn metrics(
+    monitors: &Option<SystemMonitors>,
+    weather: &WeatherRotation,
+    fahrenheit: bool,
+) -> BTreeMap<Field, f32> {
+    let mut metrics = monitors
+        .as_ref()
+        .map(|m| m.values.clone())
+        .unwrap_or_default();
+    if let Some(data) = weather.active().data() {
+        let data = if fahrenheit {
+            data.to_fahrenheit()
+        } else {
+            data.clone()
+        };
+        metrics.extend([
+            (Field::WeatherCurrent, data.current),
+            (Field::WeatherMin, data.min),
+            (Field::WeatherMax, data.max),
+        ]);
+    }
+    metrics
+}
+
+/// Reads the running processes and user idle time, when rules need them
+#[derive(Default)]
+pub struct Probe {
+    system: System,
+    /// Session bus connection, once tried
+    #[cfg(target_os = "linux")]
+    session: Option<Option<zbus::Connection>>,
+}
+
+impl Probe {
+    /// Take a snapshot of the system state for the rules
+    pub async fn snapshot(&mut self, rules: &[Rule], metrics: BTreeMap<Field, f32>) -> Snapshot {
+        let uses = |f: fn(&Condition) -> bool| rules.iter().any(|rule| f(&rule.when));
+        let mut snapshot = Snapshot {
+            metrics,
+            ..Default::default()
+        };
+        if uses(|c| matches!(c, Condition::Process { .. })) {
+            self.system.refresh_processes_specifics(
+                ProcessesToUpdate::All,
+                true,
+                ProcessRefreshKind::nothing(),
+            );
+            snapshot.processes = self
+                .system
+                .processes()
+                .values()
+                .map(|p| process_name(&p.name().to_string_lossy()))
+                .collect();
+        }
+        if uses(|c| matches!(c, Condition::Idle { .. })) {
+            snapshot.idle = self.idle_time().await;
+        }
+        snapshot
+    }
+
+    /// Time since the last input, from the desktop's idle monitor
+    #[cfg(target_os = "linux")]
+    async fn idle_time(&mut self) -> Option<Duration> {
+        if self.session.is_none() {
+            let conn = zbus::Connection::session().await.inspect_err(|e| {
+                eprintln!("warning: failed to connect to the session bus for idle time: {e}")
+            });
+            self.session = Some(conn.ok());
+        }
+        idle::idle_time(self.session.as_ref()?.as_ref()?).await
+    }
+
+    /// Time since the last input
+    #[cfg(target_os = "windows")]
+    async fn idle_time(&mut self) -> Option<Duration> {
+        use windows::Win32::System::SystemInformation::GetTickCount;
+        use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        unsafe {
+            if !GetLastInputInfo(&mut info).as_bool() {
+                return None;
+            }
+            let elapsed = GetTickCount().wrapping_sub(info.dwTime);
+            Some(Duration::from_millis(elapsed.into()))
+        }
+    }
+
+    /// Idle time isn't available on this platform
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    async fn idle_time(&mut self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod idle {
+    use super::*;
+
+    #[zbus::proxy(
+        interface = "org.gnome.Mutter.IdleMonitor",
+        default_service = "org.gnome.Mutter.IdleMonitor",
+        default_path = "/org/gnome/Mutter/IdleMonitor/Core"
+    )]
+    trait MutterIdleMonitor {
+        fn get_idletime(&self) -> zbus::Result<u64>;
+    }
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.ScreenSaver",
+        default_service = "org.freedesktop.ScreenSaver",
+        default_path = "/org/freedesktop/ScreenSaver"
+    )]
+    trait ScreenSaver {
+        fn get_session_idle_time(&self) -> zbus::Result<u32>;
+    }
+
+    /// Idle time in milliseconds from gnome, or from the screensaver service on kde and others
+    pub async fn idle_time(conn: &zbus::Connection) -> Option<Duration> {
+        if let Ok(mutter) = MutterIdleMonitorProxy::new(conn).await {
+            if let Ok(millis) = mutter.get_idletime().await {
+                return Some(Duration::from_millis(millis));
+            }
+        }
+        let screensaver = ScreenSaverProxy::new(conn).await.ok()?;
+        let millis = screensaver.get_session_idle_time().await.ok()?;
+        Some(Duration::from_millis(millis.into()))
+    }
+}
@@ -0,0 +1,148 @@
+//! Running user scripts in the daemon, and applying what they request
+
+use std::path::PathBuf;
+
+use either::Either;
+use notify_rust::Notification;
+use tokio::sync::mpsc::UnboundedSender;
+use zoom_sync_core::Board;
+
+use super::commands::{TrayCommand, TrayState};
+use super::{
+    encode_and_send_gif, encode_and_send_image, notify_error, parse_hex_color, rules, show_screen,
+    SystemMonitors,
+};
+use crate::clock;
+use crate::config::Config;
+use crate::info::apply_system;
+use crate::scripts::{Event, ScriptAction, ScriptInput, Scripts};
+use crate::sources::WeatherOverrides;
+use crate::weather::{WeatherData, WeatherRotation};
+
+/// Load the scripts, if enabled
+pub fn build(config: &Config) -> Option<Scripts> {
+    if !config.scripts.enabled {
+        return None;
+    }
+    let dir = Config::scripts_dir()?;
+    println!("loading scripts from {}", dir.display());
+    Some(Scripts::new(dir, &config.scripts))
+}
+
+/// Reload changed scripts, then run the function for an event and apply the requested actions.
+/// Errors are reported without stopping the daemon or the other scripts.
+pub fn run(
+    scripts: &mut Scripts,
+    event: Event,
+    board: &mut dyn Board,
+    state: &mut TrayState,
+    monitors: &Option<SystemMonitors>,
+    weather: &WeatherRotation,
+    tx: &UnboundedSender<TrayCommand>,
+) {
+    let mut errors = scripts.reload();
+    let fahrenheit = state.config.general.fahrenheit;
+    let input = ScriptInput {
+        metrics: rules::metrics(monitors, weather, fahrenheit),
+        weather: weather.active().data().map(|data| {
+            if fahrenheit {
+                data.to_fahrenheit()
+            } else {
+                data.clone()
+            }
+        }),
+        screen: state.current_screen.clone(),
+    };
+    let (actions, run_errors) = scripts.run(event, input);
+    errors.extend(run_errors);
+    for error in errors {
+        eprintln!("script error: {error}");
+        notify_error(&format!("Script error: {error}"));
+    }
+    for action in actions {
+        if let Err(e) = apply(action, board, state, weather, tx) {
+            eprintln!("script action failed: {e}");
+        }
+    }
+}
+
+fn apply(
+    action: ScriptAction,
+    board: &mut dyn Board,
+    state: &mut TrayState,
+    weather: &WeatherRotation,
+    tx: &UnboundedSender<TrayCommand>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fahrenheit = state.config.general.fahrenheit;
+    let ranges = state.config.general.ranges;
+    match action {
+        ScriptAction::SetScreen(id) => show_screen(board, state, id),
+        ScriptAction::SetSystemInfo { cpu, gpu, download } => apply_system(
+            board,
+            fahrenheit,
+            &ranges,
+            &mut Either::Right(cpu),
+            &Either::Right(gpu),
+            &mut Either::Right(download),
+        )?,
+        ScriptAction::SetWeather {
+            wmo,
+            is_day,
+            current,
+            min,
+            max,
+        } => {
+            let data = WeatherData {
+                wmo,
+                is_day,
+                ..Default::default()
+            };
+            // values from scripts are already in the units shown on the keyboard
+            let values = WeatherOverrides {
+                current: Some(current),
+                min: Some(min),
+                max: Some(max),
+            };
+            weather
+                .active()
+                .push(board, &data, fahrenheit, &ranges, values)?;
+        },
+        ScriptAction::SetTime(at) => {
+            let use_12hr = state.config.general.use_12hr_time;
+            let time = clock::resolve(at, state.clock_zone(), None)?;
+            crate::set_time(board, time, use_12hr)?;
+        },
+        ScriptAction::UploadImage(path) => upload(board, state, tx, path, false)?,
+        ScriptAction::UploadGif(path) => upload(board, state, tx, path, true)?,
+        ScriptAction::Notify(body) => {
+            Notification::new()
+                .summary("zoom-sync: Script")
+                .body(&body)
+                .timeout(10000)
+                .show()?;
+        },
+    }
+    Ok(())
+}
+
+/// Encode an image or animation in the background, then send it to be uploaded
+fn upload(
+    board: &mut dyn Board,
+    state: &TrayState,
+    tx: &UnboundedSender<TrayCommand>,
+    path: PathBuf,
+    animated: bool,
+) -> Result<(), &'static str> {
+    let size = board
+        .as_screen_size()
+        .ok_or("board does not support media")?;
+    let bg = parse_hex_color(&state.config.media.background_color).unwrap_or([0, 0, 0]);
+    let nearest = state.config.media.use_nearest_neighbor;
+    let tx = tx.clone();
+    if animated {
+        tokio::spawn(encode_and_send_gif(path, size, bg, nearest, tx));
+    } else {
+        tokio::spawn(encode_and_send_image(path, size, bg, nearest, tx));
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! System sleep and resume detection
+
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::clock::ClockChange;
+
+/// Forward clock jump that is taken as a resume when logind isn't available
+const RESUME_JUMP: TimeDelta = TimeDelta::seconds(30);
+
+/// Minimum time between handling resumes, so a burst of signals or jumps reconnects once
+pub const RESUME_DEBOUNCE: Duration = Duration::from_secs(30);
+
+pub enum SleepEvent {
+    /// The system is about to sleep, and waits for the sender to be used or dropped
+    Suspending(oneshot::Sender<()>),
+    Resumed,
+    /// Sleep signals can't be watched, so resumes are detected from clock jumps instead
+    Unavailable,
+}
+
+/// Check if a clock change looks like a resume from sleep, where the wall clock moved on while
+/// the monotonic clock was stopped. Clock steps look the same, so this is only a fallback for
+/// when logind isn't available.
+pub fn is_resume(change: &ClockChange) -> bool {
+    matches!(change, ClockChange::Jump(delta) if *delta >= RESUME_JUMP)
+}
+
+#[cfg(target_os = "linux")]
+pub use logind::watch_logind;
+
+#[cfg(target_os = "linux")]
+mod logind {
+    use futures::StreamExt;
+    use zbus::zvariant::OwnedFd;
+
+    use super::*;
+
+    /// How long to hold off sleep while the screen is updated
+    const SLEEP_DELAY: Duration = Duration::from_secs(2);
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.login1.Manager",
+        default_service = "org.freedesktop.login1",
+        default_path = "/org/freedesktop/login1"
+    )]
+    trait Logind {
+        fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+        #[zbus(signal)]
+        fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+    }
+
+    /// Take a delay lock, so sleep waits until it's released or the logind timeout passes
+    async fn inhibit(logind: &LogindProxy<'_>) -> Option<OwnedFd> {
+        logind
+            .inhibit(
+                "sleep",
+                "zoom-sync",
+                "Updating the keyboard screen",
+                "delay",
+            )
+            .await
+            .inspect_err(|e| eprintln!("warning: failed to take sleep delay lock: {e}"))
+            .ok()
+    }
+
+    /// Forward logind sleep signals until the receiver is dropped
+    pub async fn watch_logind(tx: mpsc::UnboundedSender<SleepEvent>) -> zbus::Result<()> {
+        let conn = zbus::Connection::system().await?;
+        let logind = LogindProxy::new(&conn).await?;
+        let mut signals = logind.receive_prepare_for_sleep().await?;
+        let mut lock = inhibit(&logind).await;
+        while let Some(signal) = signals.next().await {
+            if signal.args()?.start {
+                let (done, ack) = oneshot::channel();
+                if tx.send(SleepEvent::Suspending(done)).is_err() {
+                    break;
+                }
+                let _ = tokio::time::timeout(SLEEP_DELAY, ack).await;
+                drop(lock.take());
+            } else {
+                lock = inhibit(&logind).await;
+                if tx.send(SleepEvent::Resumed).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+//! Exponential backoff for retrying failed weather fetches
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Initial delay after the first failure
+pub const BACKOFF_BASE: Duration = Duration::from_secs(30);
+
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            failures: 0,
+        }
+    }
+
+    /// Reset after a successful attempt
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Record a failed attempt, returning how long to wait before the next one
+    pub fn failure(&mut self) -> Duration {
+        let delay = self.delay(jitter());
+        self.failures = self.failures.saturating_add(1);
+        delay
+    }
+
+    /// Double the delay for each consecutive failure up to the maximum, then pick a point in the
+    /// upper half of it with a jitter value in `0..1`, so clients don't retry in lockstep
+    fn delay(&self, jitter: f64) -> Duration {
+        let delay = self
+            .base
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(self.max);
+        delay.mul_f64(0.5 + jitter.clamp(0., 1.) / 2.)
+    }
+}
+
+/// Cheap jitter in `0..1` from the clock, which is plenty for spreading out retries
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    nanos as f64 / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential() {
+        let mut backoff = Backoff::new(Duration::from_secs(30), Duration::from_secs(300));
+        assert_eq!(backoff.delay(1.), Duration::from_secs(30));
+        assert_eq!(backoff.delay(0.), Duration::from_secs(15));
+        backoff.failures = 2;
+        assert_eq!(backoff.delay(1.), Duration::from_secs(120));
+        // capped at the maximum, even after many failures
+        backoff.failures = 40;
+        assert_eq!(backoff.delay(1.), Duration::from_secs(300));
+        backoff.reset();
+        assert!(backoff.failure() <= Duration::from_secs(30));
+        assert!(backoff.failure() >= Duration::from_secs(30));
+    }
+}
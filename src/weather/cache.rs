@@ -0,0 +1,101 @@
+//! Persistent cache of the last successful weather fetch
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use super::geolocation::Geolocation;
+use super::WeatherData;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WeatherCache {
+    /// Last known coordinates
+    pub coords: Option<(f32, f32)>,
+    /// Last fetched weather, in celsius
+    pub data: Option<WeatherData>,
+    /// Unix timestamp of the last successful fetch, in seconds
+    pub fetched_at: Option<u64>,
+    /// Coordinates for previously resolved city names
+    #[serde(default)]
+    pub locations: BTreeMap<String, (f32, f32)>,
+    /// Last ip geolocation, reused until the public ip changes
+    #[serde(default)]
+    pub geolocation: Option<Geolocation>,
+    /// Name of the location this cache is for, when rotating between several
+    #[serde(skip)]
+    name: Option<String>,
+}
+
+impl WeatherCache {
+    /// Get the cache file path for this platform, with a separate file for each named location
+    pub fn path(name: Option<&str>) -> Option<PathBuf> {
+        let file = match name {
+            Some(name) => {
+                let slug: String = name
+                    .to_lowercase()
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '-' })
+                    .collect();
+                format!("weather-{slug}.json")
+            },
+            None => "weather.json".into(),
+        };
+        ProjectDirs::from("", "", "zoom-sync").map(|dirs| dirs.cache_dir().join(file))
+    }
+
+    /// Load the cache from disk, or start empty if it doesn't exist or is invalid
+    pub fn load() -> Self {
+        Self::load_named(None)
+    }
+
+    /// Load the cache for a named location
+    pub fn load_named(name: Option<&str>) -> Self {
+        let contents = Self::path(name).and_then(|path| fs::read_to_string(path).ok());
+        let cache = match contents {
+            Some(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("warning: ignoring invalid weather cache: {e}");
+                Self::default()
+            }),
+            None => Self::default(),
+        };
+        Self {
+            name: name.map(Into::into),
+            ..cache
+        }
+    }
+
+    /// Save the cache to disk
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(self.name.as_deref()).ok_or("could not determine cache directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Store a successful fetch
+    pub fn update(&mut self, coords: (f32, f32), data: WeatherData) {
+        self.coords = Some(coords);
+        self.data = Some(data);
+        self.fetched_at = Some(unix_now());
+    }
+
+    /// Time since the last successful fetch
+    pub fn age(&self) -> Option<Duration> {
+        self.fetched_at
+            .map(|t| Duration::from_secs(unix_now().saturating_sub(t)))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,176 @@
+//! Forecast modes, and helpers for summarizing forecasts over a window of time
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{FixedOffset, NaiveTime, Offset, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What weather to show
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForecastMode {
+    /// Current conditions and today's temperature range
+    #[default]
+    Current,
+    /// Conditions and temperature range over the next N hours
+    Hours(u8),
+    /// Tomorrow's forecast, after the evening hour
+    Tomorrow,
+}
+
+impl FromStr for ForecastMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "current" | "today" => Ok(Self::Current),
+            "tomorrow" => Ok(Self::Tomorrow),
+            hours => hours
+                .strip_suffix('h')
+                .and_then(|h| h.parse().ok())
+                .filter(|h| *h > 0)
+                .map(Self::Hours)
+                .ok_or_else(|| {
+                    format!("invalid forecast '{s}', must be current, tomorrow, or hours (ie 6h)")
+                }),
+        }
+    }
+}
+
+impl Display for ForecastMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForecastMode::Current => f.write_str("current"),
+            ForecastMode::Hours(hours) => write!(f, "{hours}h"),
+            ForecastMode::Tomorrow => f.write_str("tomorrow"),
+        }
+    }
+}
+
+impl ForecastMode {
+    /// Resolve tomorrow's forecast into the current conditions until the evening hour, at the
+    /// local time of the forecast location
+    pub fn resolve(self, evening_hour: u8, local_time: NaiveTime) -> Self {
+        match self {
+            Self::Tomorrow if local_time.hour() < evening_hour as u32 => Self::Current,
+            mode => mode,
+        }
+    }
+}
+
+/// Forecast options for a request to a provider
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Forecast {
+    pub mode: ForecastMode,
+    /// Use the apparent ("feels like") temperature instead of the air temperature
+    pub apparent: bool,
+    /// Hour of the day at the location to switch to tomorrow's forecast
+    pub evening_hour: u8,
+}
+
+impl Forecast {
+    /// Forecast mode to show at the local time of the location
+    pub fn mode_at(&self, local_time: NaiveTime) -> ForecastMode {
+        self.mode.resolve(self.evening_hour, local_time)
+    }
+}
+
+/// Approximate the utc offset of a longitude from the sun, for providers that don't report the
+/// timezone of the location
+pub fn solar_offset(long: f32) -> FixedOffset {
+    let hours = (long / 15.).round() as i32;
+    FixedOffset::east_opt(hours * 3600).unwrap_or_else(|| Utc.fix())
+}
+
+/// A step of a forecast, for summarizing a window of time
+#[derive(Clone, Copy, Debug)]
+pub struct Step {
+    pub wmo: u8,
+    pub temp: f32,
+    /// Precipitation amount in mm
+    pub precipitation: f32,
+    /// Wind gusts in m/s, if reported
+    pub gust: Option<f32>,
+}
+
+/// Summarize forecast steps into a WMO code and temperature range. The condition is taken from
+/// the wettest step, or the most severe code when there is no precipitation.
+pub fn summarize(steps: &[Step]) -> Option<(u8, f32, f32)> {
+    let wettest = steps
+        .iter()
+        .filter(|s| s.precipitation > 0.)
+        .max_by(|a, b| a.precipitation.total_cmp(&b.precipitation));
+    let wmo = match wettest {
+        Some(step) => step.wmo,
+        None => steps.iter().map(|s| s.wmo).max()?,
+    };
+    let min = steps.iter().map(|s| s.temp).fold(f32::INFINITY, f32::min);
+    let max = steps
+        .iter()
+        .map(|s| s.temp)
+        .fold(f32::NEG_INFINITY, f32::max);
+    Some((wmo, min, max))
+}
+
+/// Total precipitation and strongest wind gusts over forecast steps
+pub fn totals(steps: &[Step]) -> (Option<f32>, Option<f32>) {
+    let precipitation = (!steps.is_empty()).then(|| steps.iter().map(|s| s.precipitation).sum());
+    let gusts = steps.iter().filter_map(|s| s.gust).reduce(f32::max);
+    (precipitation, gusts)
+}
+
+/// Approximate the apparent temperature in celsius, from the air temperature, relative humidity
+/// in percent, and wind speed in m/s (Australian BoM formula, as used by open-meteo)
+pub fn apparent_temperature(temp: f32, humidity: f32, wind: f32) -> f32 {
+    let vapour_pressure = humidity / 100. * 6.105 * (17.27 * temp / (237.7 + temp)).exp();
+    temp + 0.33 * vapour_pressure - 0.7 * wind - 4.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes() {
+        assert_eq!("6h".parse(), Ok(ForecastMode::Hours(6)));
+        assert_eq!("tomorrow".parse(), Ok(ForecastMode::Tomorrow));
+        assert!("0h".parse::<ForecastMode>().is_err());
+        let evening = NaiveTime::from_hms_opt(19, 30, 0).unwrap();
+        assert_eq!(
+            ForecastMode::Tomorrow.resolve(18, evening),
+            ForecastMode::Tomorrow
+        );
+        assert_eq!(
+            ForecastMode::Tomorrow.resolve(20, evening),
+            ForecastMode::Current
+        );
+        assert_eq!(solar_offset(139.7).local_minus_utc(), 9 * 3600);
+        assert_eq!(solar_offset(-74.).local_minus_utc(), -5 * 3600);
+    }
+
+    #[test]
+    fn summarize_steps() {
+        let step = |wmo, temp, precipitation| Step {
+            wmo,
+            temp,
+            precipitation,
+            gust: None,
+        };
+        // the rain shower at 18:00 matters more than the clouds
+        let steps = [step(3, 12., 0.), step(80, 9., 2.5), step(61, 8., 0.4)];
+        assert_eq!(summarize(&steps), Some((80, 8., 12.)));
+        let dry = [step(0, 20., 0.), step(2, 22., 0.)];
+        assert_eq!(summarize(&dry), Some((2, 20., 22.)));
+        assert_eq!(summarize(&[]), None);
+        let gusty = [Step {
+            gust: Some(14.),
+            ..step(61, 8., 0.4)
+        }];
+        assert_eq!(totals(&steps), (Some(2.9), None));
+        assert_eq!(totals(&gusty), (Some(0.4), Some(14.)));
+        assert_eq!(totals(&[]), (None, None));
+        // humid and still feels warmer, windy feels colder
+        assert!(apparent_temperature(30., 80., 0.) > 30.);
+        assert!(apparent_temperature(5., 50., 10.) < 0.);
+    }
+}
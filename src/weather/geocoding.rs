@@ -0,0 +1,171 @@
+//! City name geocoding, using the open-meteo geocoding api by default
+
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{stdin, stdout, Write};
+
+use serde::Deserialize;
+
+use super::cache::WeatherCache;
+use super::{fetch, ProviderArgs};
+
+/// Default open-meteo compatible geocoding endpoint
+pub const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+
+/// Maximum number of places to consider for a search
+const MAX_RESULTS: usize = 10;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Place {
+    pub name: String,
+    pub latitude: f32,
+    pub longitude: f32,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub country_code: Option<String>,
+    /// State or region
+    #[serde(default)]
+    pub admin1: Option<String>,
+}
+
+impl Place {
+    /// Check if the place matches a qualifier, ie "DE", "Germany", or "Bavaria"
+    fn matches(&self, qualifier: &str) -> bool {
+        let eq = |field: &Option<String>| {
+            field
+                .as_deref()
+                .is_some_and(|v| v.eq_ignore_ascii_case(qualifier))
+        };
+        eq(&self.country_code) || eq(&self.country) || eq(&self.admin1)
+    }
+}
+
+impl Display for Place {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        for part in [&self.admin1, &self.country].into_iter().flatten() {
+            if part != &self.name {
+                write!(f, ", {part}")?;
+            }
+        }
+        write!(f, " [{}, {}]", self.latitude, self.longitude)
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    results: Vec<Place>,
+}
+
+/// Parse a geocoding response, keeping places matching the optional qualifier
+fn parse(body: &str, qualifier: Option<&str>) -> Result<Vec<Place>, serde_json::Error> {
+    let res: Response = serde_json::from_str(body)?;
+    Ok(res
+        .results
+        .into_iter()
+        .filter(|place| qualifier.is_none_or(|q| place.matches(q)))
+        .collect())
+}
+
+/// Search for places by name. A qualifier for the country or region can be given after a comma,
+/// ie "Berlin, DE" or "Springfield, Illinois".
+pub async fn search(args: &ProviderArgs, query: &str) -> Result<Vec<Place>, Box<dyn Error>> {
+    let (name, qualifier) = match query.split_once(',') {
+        Some((name, qualifier)) => (name.trim(), Some(qualifier.trim())),
+        None => (query.trim(), None),
+    };
+    println!("searching for {query} at {} ...", args.geocoding_url);
+    let request = args.client()?.get(&args.geocoding_url).query(&[
+        ("name", name),
+        ("count", &MAX_RESULTS.to_string()),
+        ("language", "en"),
+        ("format", "json"),
+    ]);
+    Ok(parse(&fetch(request).await?, qualifier)?)
+}
+
+/// Resolve a city name into coordinates, using previously resolved coordinates from the cache.
+/// When several places match, the user is asked to pick one if interactive, otherwise the first
+/// (most relevant) match is used.
+pub async fn resolve(
+    args: &ProviderArgs,
+    query: &str,
+    cache: &mut WeatherCache,
+    interactive: bool,
+) -> Result<(f32, f32), Box<dyn Error>> {
+    let key = query.trim().to_lowercase();
+    if let Some(coords) = cache.locations.get(&key) {
+        return Ok(*coords);
+    }
+
+    let places = search(args, query).await?;
+    let place = match places.as_slice() {
+        [] => return Err(format!("no places found for {query}").into()),
+        [place] => place,
+        [first, ..] if !interactive => {
+            eprintln!(
+                "warning: {} places found for {query}, using {first}",
+                places.len()
+            );
+            first
+        },
+        _ => choose(query, &places)?,
+    };
+    println!("resolved {query} to {place}");
+
+    let coords = (place.latitude, place.longitude);
+    cache.locations.insert(key, coords);
+    if let Err(e) = cache.save() {
+        eprintln!("warning: failed to save weather cache: {e}");
+    }
+    Ok(coords)
+}
+
+/// Ask the user to pick one of several matching places
+fn choose<'a>(query: &str, places: &'a [Place]) -> Result<&'a Place, Box<dyn Error>> {
+    println!("multiple places found for {query}:");
+    for (i, place) in places.iter().enumerate() {
+        println!("  {}) {place}", i + 1);
+    }
+    print!("select a place [1-{}]: ", places.len());
+    stdout().flush()?;
+
+    let mut input = String::new();
+    stdin().read_line(&mut input)?;
+    input
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| places.get(i.checked_sub(1)?))
+        .ok_or_else(|| format!("invalid selection: {}", input.trim()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_results() {
+        let body = r#"{ "results": [
+            { "name": "Berlin", "latitude": 52.52437, "longitude": 13.41053,
+              "country": "Germany", "country_code": "DE", "admin1": "Land Berlin" },
+            { "name": "Berlin", "latitude": 44.46867, "longitude": -71.18508,
+              "country": "United States", "country_code": "US", "admin1": "New Hampshire" }
+        ] }"#;
+        assert_eq!(parse(body, None).unwrap().len(), 2);
+        let places = parse(body, Some("de")).unwrap();
+        assert_eq!(places.len(), 1);
+        assert_eq!(
+            places[0].to_string(),
+            "Berlin, Land Berlin, Germany [52.52437, 13.41053]"
+        );
+        assert_eq!(
+            parse(body, Some("new hampshire")).unwrap()[0].latitude,
+            44.46867
+        );
+        // no matches omits the results field entirely
+        assert!(parse("{}", None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+//! IP geolocation, with fallback providers and a cached location that is only looked up again
+//! when the public ip changes
+
+use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+
+use super::cache::WeatherCache;
+use super::ProviderArgs;
+
+/// Default ipinfo-compatible geolocation endpoint
+pub const GEOLOCATION_URL: &str = "https://ipinfo.io/json";
+
+/// Default endpoint returning the public ip as plain text
+pub const IP_CHECK_URL: &str = "https://ipinfo.io/ip";
+
+#[derive(Debug, thiserror::Error)]
+pub enum GeolocationError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("invalid response: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("missing location in response")]
+    MissingLocation,
+    #[error("invalid location {0:?}")]
+    InvalidLocation(String),
+    #[error("all geolocation providers failed")]
+    AllFailed,
+}
+
+/// Ip version used to reach the geolocation api, which decides the address that is located
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    /// Whichever the system prefers
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    /// Unspecified local address to bind to, forcing connections over this ip version
+    pub fn local_address(self) -> Option<IpAddr> {
+        match self {
+            IpVersion::Any => None,
+            IpVersion::V4 => Some(Ipv4Addr::UNSPECIFIED.into()),
+            IpVersion::V6 => Some(Ipv6Addr::UNSPECIFIED.into()),
+        }
+    }
+}
+
+impl FromStr for IpVersion {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(Self::Any),
+            "v4" | "ipv4" | "4" => Ok(Self::V4),
+            "v6" | "ipv6" | "6" => Ok(Self::V6),
+            _ => Err(format!(
+                "invalid ip version '{s}', must be one of: any, v4, v6"
+            )),
+        }
+    }
+}
+
+impl Display for IpVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IpVersion::Any => "any",
+            IpVersion::V4 => "v4",
+            IpVersion::V6 => "v6",
+        })
+    }
+}
+
+/// Resolved location, with the public ip it was resolved for
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Geolocation {
+    pub ip: Option<String>,
+    pub coords: (f32, f32),
+}
+
+/// Geolocation response, accepting the ipinfo format ("loc": "lat,long") as well as separate
+/// latitude and longitude fields used by most other providers
+#[derive(Deserialize)]
+struct GeolocationResponse {
+    #[serde(default, alias = "query")]
+    ip: Option<String>,
+    #[serde(default)]
+    loc: Option<String>,
+    #[serde(default, alias = "lat")]
+    latitude: Option<f32>,
+    #[serde(default, alias = "lon")]
+    longitude: Option<f32>,
+}
+
+fn parse(body: &str) -> Result<Geolocation, GeolocationError> {
+    let info: GeolocationResponse = serde_json::from_str(body)?;
+    let coords = match (info.loc, info.latitude, info.longitude) {
+        (_, Some(lat), Some(long)) => (lat, long),
+        (Some(loc), ..) => {
+            let invalid = || GeolocationError::InvalidLocation(loc.clone());
+            let (lat, long) = loc.split_once(',').ok_or_else(invalid)?;
+            let lat = lat.trim().parse().map_err(|_| invalid())?;
+            let long = long.trim().parse().map_err(|_| invalid())?;
+            (lat, long)
+        },
+        _ => return Err(GeolocationError::MissingLocation),
+    };
+    let (lat, long) = coords;
+    if !(-90. ..=90.).contains(&lat) || !(-180. ..=180.).contains(&long) {
+        return Err(GeolocationError::InvalidLocation(format!("{lat},{long}")));
+    }
+    Ok(Geolocation {
+        ip: info.ip,
+        coords,
+    })
+}
+
+/// Build a request, authenticating with the ipinfo token for ipinfo.io urls
+fn request(args: &ProviderArgs, client: &Client, url: &str) -> RequestBuilder {
+    let request = client.get(url);
+    let is_ipinfo = Url::parse(url).is_ok_and(|url| {
+        url.host_str()
+            .is_some_and(|host| host == "ipinfo.io" || host.ends_with(".ipinfo.io"))
+    });
+    match &args.ipinfo_token {
+        Some(token) if is_ipinfo => request.bearer_auth(token),
+        _ => request,
+    }
+}
+
+async fn get_text(request: RequestBuilder) -> Result<String, GeolocationError> {
+    Ok(request.send().await?.error_for_status()?.text().await?)
+}
+
+/// Look up the location from the primary geolocation api, then each fallback in order
+async fn lookup(args: &ProviderArgs, client: &Client) -> Result<Geolocation, GeolocationError> {
+    let urls = std::iter::once(&args.geolocation_url).chain(&args.geolocation_fallbacks);
+    for url in urls {
+        println!("fetching geolocation from {url} ...");
+        match get_text(request(args, client, url))
+            .await
+            .and_then(|b| parse(&b))
+        {
+            Ok(location) => return Ok(location),
+            Err(e) => eprintln!("warning: geolocation from {url} failed: {e}"),
+        }
+    }
+    Err(GeolocationError::AllFailed)
+}
+
+/// Get the approximate coordinates for this machine. A cached location is reused as long as the
+/// public ip hasn't changed (ie after connecting to a vpn), or when the ip can't be checked.
+pub async fn get_coords(
+    args: &ProviderArgs,
+    cache: &mut WeatherCache,
+) -> Result<(f32, f32), Box<dyn std::error::Error>> {
+    let client = args
+        .client_builder()?
+        .local_address(args.ip_version.local_address())
+        .build()?;
+
+    if let Some(cached) = &cache.geolocation {
+        match get_text(request(args, &client, &args.ip_check_url)).await {
+            Ok(ip) if cached.ip.as_deref() == Some(ip.trim()) => return Ok(cached.coords),
+            Ok(ip) => println!("public ip changed to {}, updating geolocation", ip.trim()),
+            Err(e) => {
+                eprintln!("warning: failed to check public ip, using cached geolocation: {e}");
+                return Ok(cached.coords);
+            },
+        }
+    }
+
+    let location = lookup(args, &client).await?;
+    cache.geolocation = Some(location.clone());
+    Ok(location.coords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let ipinfo = parse(r#"{ "ip": "1.2.3.4", "loc": "40.7128,-74.0060" }"#).unwrap();
+        assert_eq!(ipinfo.ip.as_deref(), Some("1.2.3.4"));
+        assert_eq!(ipinfo.coords, (40.7128, -74.006));
+        let ipapi = parse(r#"{ "ip": "::1", "latitude": 52.52, "longitude": 13.4 }"#).unwrap();
+        assert_eq!(ipapi.coords, (52.52, 13.4));
+        let ip_api = parse(r#"{ "query": "1.2.3.4", "lat": 52.52, "lon": 13.4 }"#).unwrap();
+        assert_eq!(ip_api.ip.as_deref(), Some("1.2.3.4"));
+
+        // malformed responses are errors rather than panics
+        for body in [
+            r#"{ "loc": "40.7128" }"#,
+            r#"{ "loc": "north,west" }"#,
+            r#"{ "loc": "140,10" }"#,
+            r#"{ "ip": "1.2.3.4" }"#,
+            "rate limited",
+        ] {
+            assert!(parse(body).is_err(), "{body}");
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! User overrides for mapping WMO codes to weather icons
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use zoom_sync_core::WeatherIcon;
+
+use crate::config::WeatherConfig;
+
+/// Weather icon name in the config, ie "day-partly-cloudy"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IconName(pub WeatherIcon);
+
+impl TryFrom<String> for IconName {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse().map(Self)
+    }
+}
+
+impl From<IconName> for String {
+    fn from(value: IconName) -> Self {
+        value.0.to_string()
+    }
+}
+
+/// Single WMO code or inclusive range of codes, ie "45" or "51-57"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct WmoRange {
+    pub start: u8,
+    pub end: u8,
+}
+
+impl WmoRange {
+    pub fn contains(&self, wmo: u8) -> bool {
+        (self.start..=self.end).contains(&wmo)
+    }
+}
+
+impl FromStr for WmoRange {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim().parse::<u8>().map_err(|_| {
+                format!("invalid wmo code range '{s}', must be ie \"45\" or \"51-57\"")
+            })
+        };
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if start > end {
+            return Err(format!(
+                "invalid wmo code range '{s}', start is after the end"
+            ));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl TryFrom<String> for WmoRange {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for WmoRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl From<WmoRange> for String {
+    fn from(value: WmoRange) -> Self {
+        value.to_string()
+    }
+}
+
+/// Icon to show for a range of codes, either always or depending on the time of day
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IconChoice {
+    Always(IconName),
+    DayNight { day: IconName, night: IconName },
+}
+
+/// Maps WMO codes to icons, preferring user overrides over the default mapping
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IconMap {
+    pub overrides: BTreeMap<WmoRange, IconChoice>,
+    /// Icon shown for codes without an icon
+    pub fallback: WeatherIcon,
+}
+
+impl Default for IconMap {
+    fn default() -> Self {
+        Self {
+            overrides: BTreeMap::new(),
+            fallback: WeatherIcon::Cloudy,
+        }
+    }
+}
+
+impl IconMap {
+    pub fn from_config(config: &WeatherConfig) -> Self {
+        Self {
+            overrides: config.icons.clone(),
+            fallback: config.fallback_icon.0,
+        }
+    }
+
+    /// Get the icon for a code. The narrowest matching override wins, so single codes can be
+    /// carved out of larger ranges.
+    pub fn icon(&self, wmo: u8, is_day: bool) -> WeatherIcon {
+        let choice = self
+            .overrides
+            .iter()
+            .filter(|(range, _)| range.contains(wmo))
+            .min_by_key(|(range, _)| range.end - range.start)
+            .map(|(_, choice)| choice);
+        match choice {
+            Some(IconChoice::Always(icon)) => icon.0,
+            Some(IconChoice::DayNight { day, night }) => {
+                if is_day {
+                    day.0
+                } else {
+                    night.0
+                }
+            },
+            None => WeatherIcon::from_wmo(wmo, is_day).unwrap_or_else(|| {
+                eprintln!("warning: unknown wmo code {wmo}, showing {}", self.fallback);
+                self.fallback
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides() {
+        let overrides: BTreeMap<WmoRange, IconChoice> = toml::from_str(
+            r#"
+            "45-48" = "day-clear"
+            "48" = { day = "day-partly-cloudy", night = "night-partly-cloudy" }
+            "51-55" = "cloudy"
+            "#,
+        )
+        .unwrap();
+        let map = IconMap {
+            overrides,
+            fallback: WeatherIcon::Thunderstorm,
+        };
+        assert_eq!(map.icon(45, false), WeatherIcon::DayClear);
+        assert_eq!(map.icon(48, false), WeatherIcon::NightPartlyCloudy);
+        assert_eq!(map.icon(53, true), WeatherIcon::Cloudy);
+        // defaults and fallback
+        assert_eq!(map.icon(61, true), WeatherIcon::Rainy);
+        assert_eq!(map.icon(42, true), WeatherIcon::Thunderstorm);
+
+        assert!("60-50".parse::<WmoRange>().is_err());
+        assert!(toml::from_str::<BTreeMap<WmoRange, IconChoice>>(r#""1" = "sunny""#).is_err());
+    }
+}
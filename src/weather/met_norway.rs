@@ -0,0 +1,324 @@
+//! MET Norway locationforecast api (api.met.no)
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::forecast::{
+    apparent_temperature, solar_offset, summarize, totals, Forecast, ForecastMode, Step,
+};
+use super::{fetch, is_daytime, WeatherData, WeatherError, WeatherProvider};
+
+pub const BASE_URL: &str = "https://api.met.no";
+
+pub struct MetNorway {
+    pub client: Client,
+    pub base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    properties: Properties,
+}
+
+#[derive(Deserialize)]
+struct Properties {
+    timeseries: Vec<TimeStep>,
+}
+
+#[derive(Deserialize)]
+struct TimeStep {
+    /// UTC time, ie "2024-05-01T12:00:00Z"
+    time: String,
+    data: TimeStepData,
+}
+
+#[derive(Deserialize)]
+struct TimeStepData {
+    instant: Instant,
+    next_1_hours: Option<Period>,
+    next_6_hours: Option<Period>,
+    next_12_hours: Option<Period>,
+}
+
+#[derive(Deserialize)]
+struct Instant {
+    details: Details,
+}
+
+#[derive(Deserialize)]
+struct Details {
+    air_temperature: f32,
+    relative_humidity: Option<f32>,
+    /// Wind speed in m/s
+    wind_speed: Option<f32>,
+    /// Wind gust speed in m/s
+    wind_speed_of_gust: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Period {
+    summary: Summary,
+    details: Option<PeriodDetails>,
+}
+
+#[derive(Deserialize)]
+struct PeriodDetails {
+    precipitation_amount: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Summary {
+    symbol_code: String,
+}
+
+impl WeatherProvider for MetNorway {
+    fn get_weather(
+        &self,
+        lat: f32,
+        long: f32,
+        forecast: Forecast,
+    ) -> BoxFuture<'_, Result<WeatherData, WeatherError>> {
+        Box::pin(async move {
+            // the api rejects coordinates with more than 4 decimals
+            let request = self
+                .client
+                .get(format!(
+                    "{}/weatherapi/locationforecast/2.0/compact",
+                    self.base_url
+                ))
+                .query(&[("lat", format!("{lat:.4}")), ("lon", format!("{long:.4}"))]);
+            parse(&fetch(request).await?, forecast, (lat, long))
+        })
+    }
+}
+
+impl TimeStep {
+    fn temp(&self, apparent: bool) -> f32 {
+        let details = &self.data.instant.details;
+        match (details.relative_humidity, details.wind_speed) {
+            (Some(humidity), Some(wind)) if apparent => {
+                apparent_temperature(details.air_temperature, humidity, wind)
+            },
+            _ => details.air_temperature,
+        }
+    }
+
+    /// Convert an hourly step for summarizing, skipping unknown symbols
+    fn step(&self, apparent: bool) -> Option<Step> {
+        let period = self.data.next_1_hours.as_ref()?;
+        Some(Step {
+            wmo: symbol_to_wmo(&period.summary.symbol_code)?.0,
+            temp: self.temp(apparent),
+            precipitation: period
+                .details
+                .as_ref()
+                .and_then(|d| d.precipitation_amount)
+                .unwrap_or_default(),
+            gust: self.data.instant.details.wind_speed_of_gust,
+        })
+    }
+}
+
+fn parse(body: &str, forecast: Forecast, coords: (f32, f32)) -> Result<WeatherData, WeatherError> {
+    let res: Response = serde_json::from_str(body)?;
+    let timeseries = res.properties.timeseries;
+    let now = timeseries
+        .first()
+        .ok_or(WeatherError::Missing("timeseries"))?;
+    let current = now.temp(forecast.apparent);
+    // the api doesn't report the timezone, so approximate the local time from the longitude
+    let offset = solar_offset(coords.1);
+    let local_now = DateTime::parse_from_rfc3339(&now.time)
+        .unwrap_or_else(|_| Utc::now().fixed_offset())
+        .with_timezone(&offset);
+
+    // the first steps are hourly
+    let hourly = |steps: &mut dyn Iterator<Item = &TimeStep>| -> Vec<Step> {
+        steps.filter_map(|t| t.step(forecast.apparent)).collect()
+    };
+    let summary = |steps: &[Step]| summarize(steps).ok_or(WeatherError::Missing("hourly forecast"));
+    match forecast.mode_at(local_now.time()) {
+        ForecastMode::Current => {},
+        ForecastMode::Hours(hours) => {
+            let steps = hourly(&mut timeseries.iter().take(hours as usize));
+            let (wmo, min, max) = summary(&steps)?;
+            let (precipitation, wind_gusts) = totals(&steps);
+            let is_day = now
+                .data
+                .next_1_hours
+                .as_ref()
+                .and_then(|p| symbol_to_wmo(&p.summary.symbol_code)?.1);
+            return Ok(WeatherData {
+                wmo,
+                is_day: is_day.unwrap_or_else(|| is_daytime(Some(coords))),
+                current,
+                min,
+                max,
+                precipitation,
+                wind_gusts,
+            });
+        },
+        ForecastMode::Tomorrow => {
+            let tomorrow = local_now.date_naive().succ_opt();
+            let steps = hourly(&mut timeseries.iter().filter(|t| {
+                DateTime::parse_from_rfc3339(&t.time)
+                    .is_ok_and(|time| Some(time.with_timezone(&offset).date_naive()) == tomorrow)
+            }));
+            let (wmo, min, max) = summary(&steps)?;
+            let (precipitation, wind_gusts) = totals(&steps);
+            return Ok(WeatherData {
+                wmo,
+                // show daytime icons for tomorrow's forecast
+                is_day: true,
+                current,
+                min,
+                max,
+                precipitation,
+                wind_gusts,
+            });
+        },
+    }
+
+    let symbol = [
+        &now.data.next_1_hours,
+        &now.data.next_6_hours,
+        &now.data.next_12_hours,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|p| p.summary.symbol_code.as_str())
+    .next()
+    .ok_or(WeatherError::Missing("symbol code"))?;
+
+    let (wmo, is_day) =
+        symbol_to_wmo(symbol).ok_or_else(|| WeatherError::UnknownCondition(symbol.to_string()))?;
+
+    // use the next 24 hours for the daily range
+    let temps = timeseries
+        .iter()
+        .take(24)
+        .map(|t| t.temp(forecast.apparent));
+    let min = temps.clone().fold(f32::INFINITY, f32::min);
+    let max = temps.fold(f32::NEG_INFINITY, f32::max);
+    let (precipitation, wind_gusts) = totals(&hourly(&mut timeseries.iter().take(24)));
+
+    Ok(WeatherData {
+        wmo,
+        is_day: is_day.unwrap_or_else(|| is_daytime(Some(coords))),
+        current,
+        min,
+        max,
+        precipitation,
+        wind_gusts,
+    })
+}
+
+/// Map a symbol code (ie "lightrainshowers_day") to a WMO code, and whether it's daytime when
+/// the symbol has a variant suffix
+fn symbol_to_wmo(symbol: &str) -> Option<(u8, Option<bool>)> {
+    let (base, is_day) = match symbol.rsplit_once('_') {
+        Some((base, "day" | "polartwilight")) => (base, Some(true)),
+        Some((base, "night")) => (base, Some(false)),
+        _ => (symbol, None),
+    };
+    if base.contains("thunder") {
+        return Some((95, is_day));
+    }
+    let wmo = match base {
+        "clearsky" => 0,
+        "fair" => 1,
+        "partlycloudy" => 2,
+        "cloudy" => 3,
+        "fog" => 45,
+        "lightrain" => 61,
+        "rain" => 63,
+        "heavyrain" => 65,
+        "lightsleet" | "sleet" | "lightsleetshowers" | "sleetshowers" => 66,
+        "heavysleet" | "heavysleetshowers" => 67,
+        "lightsnow" => 71,
+        "snow" => 73,
+        "heavysnow" => 75,
+        "lightrainshowers" => 80,
+        "rainshowers" => 81,
+        "heavyrainshowers" => 82,
+        "lightsnowshowers" | "snowshowers" => 85,
+        "heavysnowshowers" => 86,
+        _ => return None,
+    };
+    Some((wmo, is_day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COORDS: (f32, f32) = (59.91, 10.75);
+
+    #[test]
+    fn symbols() {
+        assert_eq!(symbol_to_wmo("clearsky_night"), Some((0, Some(false))));
+        assert_eq!(
+            symbol_to_wmo("lightrainshowers_day"),
+            Some((80, Some(true)))
+        );
+        assert_eq!(symbol_to_wmo("heavysnow"), Some((75, None)));
+        assert_eq!(
+            symbol_to_wmo("heavyrainshowersandthunder_polartwilight"),
+            Some((95, Some(true)))
+        );
+        assert_eq!(symbol_to_wmo("meteorshower"), None);
+    }
+
+    #[test]
+    fn parse_response() {
+        let step = |temp: f32, symbol: &str, rain: f32, gust: f32| {
+            format!(
+                r#"{{ "time": "2024-05-01T12:00:00Z", "data": {{
+                    "instant": {{ "details": {{
+                        "air_temperature": {temp}, "wind_speed_of_gust": {gust}
+                    }} }},
+                    "next_1_hours": {{
+                        "summary": {{ "symbol_code": "{symbol}" }},
+                        "details": {{ "precipitation_amount": {rain} }}
+                    }}
+                }} }}"#
+            )
+        };
+        let body = format!(
+            r#"{{ "properties": {{ "timeseries": [{}, {}, {}] }} }}"#,
+            step(4.5, "partlycloudy_day", 0., 6.),
+            step(-1., "snow", 1.2, 17.5),
+            step(7., "cloudy", 0., 9.)
+        );
+        let hours = Forecast {
+            mode: ForecastMode::Hours(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse(&body, hours, COORDS).unwrap(),
+            WeatherData {
+                wmo: 73,
+                is_day: true,
+                current: 4.5,
+                min: -1.,
+                max: 4.5,
+                precipitation: Some(1.2),
+                wind_gusts: Some(17.5),
+            }
+        );
+        assert_eq!(
+            parse(&body, Forecast::default(), COORDS).unwrap(),
+            WeatherData {
+                wmo: 2,
+                is_day: true,
+                current: 4.5,
+                min: -1.,
+                max: 7.,
+                precipitation: Some(1.2),
+                wind_gusts: Some(17.5),
+            }
+        );
+    }
+}
@@ -0,0 +1,933 @@
+//! Utilities for getting weather info
+
+use std::error::Error;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bpaf::Bpaf;
+use futures::future::BoxFuture;
+use reqwest::{Client, ClientBuilder, Proxy, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use zoom_sync_core::Board;
+
+use crate::config::WeatherConfig;
+use crate::sources::WeatherOverrides;
+use crate::temperature::RangeConfig;
+use crate::weather::backoff::{Backoff, BACKOFF_BASE};
+use crate::weather::cache::WeatherCache;
+use crate::weather::forecast::Forecast;
+pub use crate::weather::forecast::ForecastMode;
+pub use crate::weather::geocoding::GEOCODING_URL;
+pub use crate::weather::geolocation::{IpVersion, GEOLOCATION_URL, IP_CHECK_URL};
+pub use crate::weather::icons::{IconChoice, IconMap, IconName, WmoRange};
+pub use crate::weather::rotation::WeatherRotation;
+
+mod backoff;
+mod cache;
+mod forecast;
+mod geocoding;
+mod geolocation;
+mod icons;
+mod met_norway;
+mod open_meteo;
+mod openweathermap;
+mod rotation;
+mod sun;
+mod wttr;
+
+#[derive(Clone, Debug, Bpaf)]
+#[bpaf(adjacent)]
+pub struct Coords {
+    /// Optional coordinates to use for fetching weather data, skipping the geolocation api.
+    #[bpaf(long)]
+    #[allow(dead_code)]
+    pub coords: (),
+    /// Latitude
+    #[bpaf(positional("LAT"))]
+    pub lat: f32,
+    /// Longitude
+    #[bpaf(positional("LON"))]
+    pub long: f32,
+}
+
+/// Weather forecast options:
+#[derive(Clone, Debug, Bpaf)]
+pub enum WeatherArgs {
+    /// Disable updating weather info completely
+    #[bpaf(long("no-weather"))]
+    Disabled,
+    // default
+    Auto {
+        #[bpaf(external, optional)]
+        coords: Option<Coords>,
+        /// City to fetch weather data for, ie "Berlin, DE", resolved with the geocoding api
+        #[bpaf(long, argument("CITY"), optional)]
+        city: Option<String>,
+        #[bpaf(external(provider_args), map(Box::new))]
+        providers: Box<ProviderArgs>,
+    },
+    #[bpaf(adjacent)]
+    Manual {
+        /// Manually provide weather data, skipping the weather provider. Temperatures are shown
+        /// as given, fitted with the range policies from the tray config, and may be negative.
+        /// The day or night icon follows the sun at the configured coordinates, or the last
+        /// known location, and is the day icon when neither is known.
+        #[bpaf(short, long)]
+        #[allow(dead_code)]
+        weather: (),
+        /// WMO Index
+        #[bpaf(positional("WMO"))]
+        wmo: u8,
+        /// Current temperature
+        #[bpaf(any("CUR", Some))]
+        current: f32,
+        /// Minumum temperature
+        #[bpaf(any("MIN", Some))]
+        min: f32,
+        /// Maximum temperature
+        #[bpaf(any("MAX", Some))]
+        max: f32,
+    },
+}
+
+/// Default timeout for weather and geolocation requests
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default hour of the day to switch to tomorrow's forecast
+pub const EVENING_HOUR: u8 = 18;
+
+/// Weather provider options:
+#[derive(Clone, Debug, Bpaf)]
+pub struct ProviderArgs {
+    /// Weather provider to fetch forecasts from
+    /// [open-meteo|met.no|openweathermap|wttr.in]
+    #[bpaf(
+        long,
+        argument("PROVIDER"),
+        fallback(Provider::OpenMeteo),
+        display_fallback
+    )]
+    pub provider: Provider,
+    /// Secondary weather provider, used when the primary one fails
+    #[bpaf(long, argument("PROVIDER"), optional)]
+    pub fallback_provider: Option<Provider>,
+    /// API key for providers that require one (openweathermap)
+    #[bpaf(long, argument("KEY"), optional)]
+    pub api_key: Option<String>,
+    /// Base url for the primary weather provider, ie a self-hosted open-meteo instance
+    #[bpaf(long, argument("URL"), optional)]
+    pub base_url: Option<String>,
+    /// Url for the geolocation api, returning json with a "loc" field (ipinfo) or "latitude"
+    /// and "longitude" fields
+    #[bpaf(
+        long,
+        argument("URL"),
+        fallback(GEOLOCATION_URL.into()),
+        display_fallback
+    )]
+    pub geolocation_url: String,
+    /// Fallback geolocation apis, tried in order when the primary one fails
+    #[bpaf(long("geolocation-fallback"), argument("URL"), many)]
+    pub geolocation_fallbacks: Vec<String>,
+    /// Token for the ipinfo.io geolocation api
+    #[bpaf(long, argument("TOKEN"), optional)]
+    pub ipinfo_token: Option<String>,
+    /// Ip version to geolocate, ie v6 to locate the ipv6 address [any|v4|v6]
+    #[bpaf(long, argument("VERSION"), fallback(IpVersion::Any), display_fallback)]
+    pub ip_version: IpVersion,
+    /// Url returning the public ip as plain text, checked before reusing the cached geolocation
+    #[bpaf(
+        long,
+        argument("URL"),
+        fallback(IP_CHECK_URL.into()),
+        display_fallback
+    )]
+    pub ip_check_url: String,
+    /// Url for the open-meteo compatible geocoding api, used to find cities
+    #[bpaf(
+        long,
+        argument("URL"),
+        fallback(GEOCODING_URL.into()),
+        display_fallback
+    )]
+    pub geocoding_url: String,
+    /// Proxy for weather and geolocation requests, ie http://proxy.local:3128
+    #[bpaf(long, argument("URL"), optional)]
+    pub proxy: Option<String>,
+    /// Timeout for weather and geolocation requests
+    #[bpaf(
+        long,
+        argument("DURATION"),
+        fallback(REQUEST_TIMEOUT.into()),
+        display_fallback
+    )]
+    pub timeout: humantime::Duration,
+    /// Weather to show: current conditions, the next N hours (ie 6h), or tomorrow's forecast
+    /// after the evening hour [current|<N>h|tomorrow]
+    #[bpaf(
+        long,
+        argument("MODE"),
+        fallback(ForecastMode::Current),
+        display_fallback
+    )]
+    pub forecast: ForecastMode,
+    /// Hour of the day (0-23) at the weather location to switch to tomorrow's forecast
+    #[bpaf(long, argument("HOUR"), fallback(EVENING_HOUR), display_fallback)]
+    pub evening_hour: u8,
+    /// Show the apparent ("feels like") temperature instead of the air temperature
+    #[bpaf(long)]
+    pub apparent: bool,
+}
+
+impl ProviderArgs {
+    /// Build an http client with the configured proxy and timeout
+    fn client(&self) -> Result<Client, Box<dyn Error>> {
+        Ok(self.client_builder()?.build()?)
+    }
+
+    fn client_builder(&self) -> Result<ClientBuilder, Box<dyn Error>> {
+        let mut builder = Client::builder()
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION"),
+                " ",
+                env!("CARGO_PKG_REPOSITORY")
+            ))
+            .timeout(*self.timeout);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        Ok(builder)
+    }
+}
+
+/// Supported weather providers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Provider {
+    /// Free forecast api from open-meteo.com
+    #[default]
+    #[serde(rename = "open-meteo")]
+    OpenMeteo,
+    /// Free forecast api from the norwegian meteorological institute
+    #[serde(rename = "met.no")]
+    MetNorway,
+    /// OpenWeatherMap current weather and forecast apis, requiring an api key
+    #[serde(rename = "openweathermap")]
+    OpenWeatherMap,
+    /// Free console weather service
+    #[serde(rename = "wttr.in")]
+    Wttr,
+}
+
+impl Provider {
+    /// Build the provider implementation, optionally overriding its base url
+    pub fn build(
+        self,
+        client: Client,
+        base_url: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Result<Box<dyn WeatherProvider>, WeatherError> {
+        let base_url = base_url
+            .unwrap_or(match self {
+                Provider::OpenMeteo => open_meteo::BASE_URL,
+                Provider::MetNorway => met_norway::BASE_URL,
+                Provider::OpenWeatherMap => openweathermap::BASE_URL,
+                Provider::Wttr => wttr::BASE_URL,
+            })
+            .trim_end_matches('/')
+            .to_string();
+        Ok(match self {
+            Provider::OpenMeteo => Box::new(open_meteo::OpenMeteo { client, base_url }),
+            Provider::MetNorway => Box::new(met_norway::MetNorway { client, base_url }),
+            Provider::OpenWeatherMap => Box::new(openweathermap::OpenWeatherMap {
+                client,
+                base_url,
+                api_key: api_key.ok_or(WeatherError::MissingApiKey(self))?.into(),
+            }),
+            Provider::Wttr => Box::new(wttr::Wttr { client, base_url }),
+        })
+    }
+}
+
+impl FromStr for Provider {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "open-meteo" | "openmeteo" => Ok(Self::OpenMeteo),
+            "met.no" | "metno" | "met-norway" => Ok(Self::MetNorway),
+            "openweathermap" | "owm" => Ok(Self::OpenWeatherMap),
+            "wttr.in" | "wttr" => Ok(Self::Wttr),
+            _ => Err(format!(
+                "invalid provider '{s}', must be one of: open-meteo, met.no, openweathermap, wttr.in"
+            )),
+        }
+    }
+}
+
+impl Display for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Provider::OpenMeteo => "open-meteo",
+            Provider::MetNorway => "met.no",
+            Provider::OpenWeatherMap => "openweathermap",
+            Provider::Wttr => "wttr.in",
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WeatherError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("invalid response: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("missing {0} in response")]
+    Missing(&'static str),
+    #[error("unknown weather condition {0}")]
+    UnknownCondition(String),
+    #[error("{0} requires an api key")]
+    MissingApiKey(Provider),
+}
+
+/// A source of weather forecasts
+pub trait WeatherProvider {
+    /// Fetch the weather for the given coordinates and forecast mode, with temperatures in celsius
+    fn get_weather(
+        &self,
+        lat: f32,
+        long: f32,
+        forecast: Forecast,
+    ) -> BoxFuture<'_, Result<WeatherData, WeatherError>>;
+}
+
+/// Send a request, returning the response body when successful
+async fn fetch(request: RequestBuilder) -> Result<String, WeatherError> {
+    Ok(request.send().await?.error_for_status()?.text().await?)
+}
+
+/// Check if it's currently daytime from the sun position at the given coordinates, for when a
+/// provider doesn't say. Without coordinates, the day icon is shown.
+fn is_daytime(coords: Option<(f32, f32)>) -> bool {
+    match coords {
+        Some((lat, long)) => sun::is_day(chrono::Utc::now(), lat, long),
+        None => {
+            eprintln!("warning: no known location for sunrise and sunset, showing the day icon");
+            true
+        },
+    }
+}
+
+/// Find the coordinates for a city name, or the current geolocation if not given
+async fn locate(
+    providers: &ProviderArgs,
+    city: Option<&str>,
+    cache: &mut WeatherCache,
+    interactive: bool,
+) -> Result<(f32, f32), Box<dyn Error>> {
+    match city {
+        Some(city) => geocoding::resolve(providers, city, cache, interactive).await,
+        None => geolocation::get_coords(providers, cache).await,
+    }
+}
+
+/// Weather data from API
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeatherData {
+    pub wmo: u8,
+    pub is_day: bool,
+    pub current: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Total precipitation in mm over the forecast period, if reported
+    #[serde(default)]
+    pub precipitation: Option<f32>,
+    /// Strongest wind gusts in m/s over the forecast period, if reported
+    #[serde(default)]
+    pub wind_gusts: Option<f32>,
+}
+
+impl WeatherData {
+    /// Convert temperatures from celsius to fahrenheit
+    pub fn to_fahrenheit(&self) -> Self {
+        let convert = |c: f32| c * 9. / 5. + 32.;
+        Self {
+            current: convert(self.current),
+            min: convert(self.min),
+            max: convert(self.max),
+            ..*self
+        }
+    }
+}
+
+/// Weather to display when there is no recent enough data. Values are unitless.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnknownWeather {
+    pub wmo: u8,
+    pub current: u8,
+    pub min: u8,
+    pub max: u8,
+}
+
+impl Default for UnknownWeather {
+    fn default() -> Self {
+        Self {
+            // cloudy icon
+            wmo: 3,
+            current: 0,
+            min: 0,
+            max: 0,
+        }
+    }
+}
+
+/// Get the weather in celsius for the configured forecast mode from the configured providers,
+/// failing over to the secondary provider when the primary one errors
+pub async fn get_weather(
+    providers: &ProviderArgs,
+    lat: f32,
+    long: f32,
+) -> Result<WeatherData, Box<dyn Error>> {
+    let client = providers.client()?;
+    let api_key = providers.api_key.as_deref();
+    let forecast = Forecast {
+        mode: providers.forecast,
+        apparent: providers.apparent,
+        evening_hour: providers.evening_hour,
+    };
+
+    let mut result = fetch_weather(
+        providers.provider,
+        &client,
+        providers.base_url.as_deref(),
+        api_key,
+        (lat, long),
+        forecast,
+    )
+    .await;
+    if let (Err(e), Some(fallback)) = (&result, providers.fallback_provider) {
+        eprintln!(
+            "warning: failed to fetch weather from {}: {e}",
+            providers.provider
+        );
+        // the base url only applies to the primary provider
+        result = fetch_weather(fallback, &client, None, api_key, (lat, long), forecast).await;
+    }
+    Ok(result?)
+}
+
+async fn fetch_weather(
+    provider: Provider,
+    client: &Client,
+    base_url: Option<&str>,
+    api_key: Option<&str>,
+    (lat, long): (f32, f32),
+    forecast: Forecast,
+) -> Result<WeatherData, WeatherError> {
+    println!(
+        "fetching {} weather from {provider} for [{lat}, {long}] ...",
+        forecast.mode
+    );
+    provider
+        .build(client.clone(), base_url, api_key)?
+        .get_weather(lat, long, forecast)
+        .await
+}
+
+/// Push weather data in celsius to the board, converting units and applying custom source
+/// overrides
+fn push_weather(
+    board: &mut dyn Board,
+    data: &WeatherData,
+    fahrenheit: bool,
+    ranges: &RangeConfig,
+    icons: &IconMap,
+    overrides: WeatherOverrides,
+) -> Result<(), Box<dyn Error>> {
+    let weather = board.as_weather().ok_or("board does not support weather")?;
+    let mut data = if fahrenheit {
+        data.to_fahrenheit()
+    } else {
+        data.clone()
+    };
+
+    // prefer values from custom sources
+    data.current = overrides.current.unwrap_or(data.current);
+    data.min = overrides.min.unwrap_or(data.min);
+    data.max = overrides.max.unwrap_or(data.max);
+
+    let (current, current_neg) =
+        ranges
+            .weather_current
+            .fit("current weather", data.current, fahrenheit);
+    let (min, min_neg) = ranges
+        .weather_min
+        .fit("minimum weather", data.min, fahrenheit);
+    let (max, max_neg) = ranges
+        .weather_max
+        .fit("maximum weather", data.max, fahrenheit);
+    let icon = if ranges.mark_negative && (current_neg || min_neg || max_neg) {
+        ranges.negative_icon.0
+    } else {
+        icons.icon(data.wmo, data.is_day)
+    };
+    weather
+        .set_weather_icon(icon, current, min, max)
+        .map_err(|e| format!("failed to set weather: {e}"))?;
+    println!(
+        "updated weather {{ wmo: {}, icon: {icon}, current: {}, min: {}, max: {} }}",
+        data.wmo, data.current, data.min, data.max
+    );
+    Ok(())
+}
+
+/// Fetch and push the weather, or push manual weather. The location is used for the day/night
+/// icon of manual weather.
+pub async fn apply_weather(
+    board: &mut dyn Board,
+    args: &mut WeatherArgs,
+    farenheit: bool,
+    ranges: &RangeConfig,
+    icons: &IconMap,
+    location: Option<(f32, f32)>,
+    overrides: WeatherOverrides,
+) -> Result<(), Box<dyn Error>> {
+    match args {
+        WeatherArgs::Disabled => println!("skipping weather"),
+        WeatherArgs::Auto {
+            coords,
+            city,
+            providers,
+        } => {
+            // attempt to backfill coordinates if not provided
+            if coords.is_none() {
+                let mut cache = WeatherCache::load();
+                let interactive = std::io::stdin().is_terminal();
+                match locate(providers, city.as_deref(), &mut cache, interactive).await {
+                    Ok((lat, long)) => {
+                        if let Err(e) = cache.save() {
+                            eprintln!("warning: failed to save weather cache: {e}");
+                        }
+                        *coords = Some(Coords {
+                            coords: (),
+                            lat,
+                            long,
+                        })
+                    },
+                    Err(e) => eprintln!("warning: failed to find location: {e}"),
+                }
+            }
+
+            // try to update weather if we have some coordinates
+            if let Some(Coords { lat, long, .. }) = *coords {
+                match get_weather(providers, lat, long).await {
+                    Ok(data) => push_weather(board, &data, farenheit, ranges, icons, overrides)?,
+                    Err(e) => eprintln!("failed to fetch weather, skipping: {e}"),
+                }
+            }
+        },
+        WeatherArgs::Manual { .. } => {
+            push_manual(board, args, farenheit, ranges, icons, location, overrides)?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Push manually provided weather, with values from custom sources taking priority
+fn push_manual(
+    board: &mut dyn Board,
+    args: &WeatherArgs,
+    fahrenheit: bool,
+    ranges: &RangeConfig,
+    icons: &IconMap,
+    location: Option<(f32, f32)>,
+    overrides: WeatherOverrides,
+) -> Result<(), Box<dyn Error>> {
+    let WeatherArgs::Manual {
+        wmo,
+        current,
+        min,
+        max,
+        ..
+    } = *args
+    else {
+        return Ok(());
+    };
+    let data = WeatherData {
+        wmo,
+        // use the configured location for the day/night icon, or the last known one
+        is_day: is_daytime(location.or_else(|| WeatherCache::load().coords)),
+        ..Default::default()
+    };
+    // manual values are already in the units shown on the keyboard, like the overrides
+    let values = WeatherOverrides {
+        current: overrides.current.or(Some(current)),
+        min: overrides.min.or(Some(min)),
+        max: overrides.max.or(Some(max)),
+    };
+    push_weather(board, &data, fahrenheit, ranges, icons, values)
+}
+
+/// Weather updates for the tray, caching the last successful fetch on disk and backing off
+/// when fetching fails
+pub struct WeatherUpdater {
+    /// Location name, when rotating between several
+    pub name: Option<String>,
+    pub args: WeatherArgs,
+    cache: WeatherCache,
+    max_age: Duration,
+    unknown: UnknownWeather,
+    icons: IconMap,
+    backoff: Backoff,
+}
+
+impl WeatherUpdater {
+    pub fn new(
+        name: Option<String>,
+        args: WeatherArgs,
+        config: &WeatherConfig,
+        refresh: Duration,
+    ) -> Self {
+        Self {
+            cache: WeatherCache::load_named(name.as_deref()),
+            name,
+            args,
+            max_age: config.max_age,
+            unknown: config.unknown,
+            icons: IconMap::from_config(config),
+            backoff: Backoff::new(BACKOFF_BASE.min(refresh), refresh),
+        }
+    }
+
+    /// Configured or last known coordinates
+    fn coords(&self) -> Option<(f32, f32)> {
+        match &self.args {
+            WeatherArgs::Auto {
+                coords: Some(Coords { lat, long, .. }),
+                ..
+            } => Some((*lat, *long)),
+            _ => self.cache.coords,
+        }
+    }
+
+    /// Time until the next sunrise or sunset at the current location, when the cached weather
+    /// icon should be re-pushed
+    pub fn until_sun_transition(&self) -> Option<Duration> {
+        if !matches!(self.args, WeatherArgs::Auto { .. }) {
+            return None;
+        }
+        let (lat, long) = self.coords()?;
+        let now = chrono::Utc::now();
+        let next = sun::next_transition(now, lat, long)?;
+        // land just after the transition
+        (next - now)
+            .to_std()
+            .ok()
+            .map(|d| d + Duration::from_secs(1))
+    }
+
+    /// Push the cached weather to the board without fetching, ie right after connecting or at
+    /// sunrise and sunset. Falls back to the unknown state when the cache is too old.
+    pub fn apply_cached(
+        &self,
+        board: &mut dyn Board,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        overrides: WeatherOverrides,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.args {
+            WeatherArgs::Auto { .. } => {},
+            WeatherArgs::Manual { .. } => {
+                return push_manual(
+                    board,
+                    &self.args,
+                    fahrenheit,
+                    ranges,
+                    &self.icons,
+                    self.coords(),
+                    overrides,
+                )
+            },
+            WeatherArgs::Disabled => return Ok(()),
+        }
+        match (&self.cache.data, self.cache.age()) {
+            (Some(data), Some(age)) if age <= self.max_age => {
+                println!(
+                    "using cached weather from {} ago",
+                    humantime::format_duration(Duration::from_secs(age.as_secs()))
+                );
+                // the sun may have set or risen since the fetch
+                let data = WeatherData {
+                    is_day: is_daytime(self.coords()),
+                    ..data.clone()
+                };
+                push_weather(board, &data, fahrenheit, ranges, &self.icons, overrides)
+            },
+            // nothing to show until the first fetch, unless sources provide some values
+            (None, _) if overrides.is_empty() => Ok(()),
+            _ => {
+                let UnknownWeather {
+                    wmo,
+                    current,
+                    min,
+                    max,
+                } = self.unknown;
+                let (current, min, max) = overrides.fit(ranges, fahrenheit, (current, min, max));
+                board
+                    .as_weather()
+                    .ok_or("board does not support weather")?
+                    .set_weather_icon(
+                        self.icons.icon(wmo, is_daytime(self.coords())),
+                        current,
+                        min,
+                        max,
+                    )?;
+                println!("no recent weather, showing unknown weather");
+                Ok(())
+            },
+        }
+    }
+
+    /// Push weather from elsewhere to the board, using the configured icons
+    pub fn push(
+        &self,
+        board: &mut dyn Board,
+        data: &WeatherData,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        overrides: WeatherOverrides,
+    ) -> Result<(), Box<dyn Error>> {
+        push_weather(board, data, fahrenheit, ranges, &self.icons, overrides)
+    }
+
+    /// Last fetched weather, in celsius
+    pub fn data(&self) -> Option<&WeatherData> {
+        self.cache.data.as_ref()
+    }
+
+    /// Fetch and push new weather. When fetching fails, the cached weather is shown instead and
+    /// the delay before the next attempt is returned.
+    pub async fn update(
+        &mut self,
+        board: &mut dyn Board,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        overrides: WeatherOverrides,
+    ) -> Result<Option<Duration>, Box<dyn Error>> {
+        if !matches!(self.args, WeatherArgs::Auto { .. }) {
+            let icons = self.icons.clone();
+            let location = self.coords();
+            apply_weather(
+                board,
+                &mut self.args,
+                fahrenheit,
+                ranges,
+                &icons,
+                location,
+                overrides,
+            )
+            .await?;
+            return Ok(None);
+        }
+
+        match self.refresh().await {
+            None => {
+                if let Some(data) = &self.cache.data {
+                    push_weather(board, data, fahrenheit, ranges, &self.icons, overrides)?;
+                }
+                Ok(None)
+            },
+            Some(delay) => {
+                self.apply_cached(board, fahrenheit, ranges, overrides)?;
+                Ok(Some(delay))
+            },
+        }
+    }
+
+    /// Fetch new weather into the cache without pushing it to the board. When fetching fails,
+    /// the delay before the next attempt is returned.
+    pub async fn refresh(&mut self) -> Option<Duration> {
+        let WeatherArgs::Auto {
+            coords,
+            city,
+            providers,
+        } = &self.args
+        else {
+            return None;
+        };
+        if let Some(name) = &self.name {
+            println!("updating weather for {name} ...");
+        }
+
+        // locate every time if coordinates aren't provided, so a changed public ip is noticed.
+        // this only costs a request for the ip, as resolved locations are cached.
+        let location = match coords {
+            Some(Coords { lat, long, .. }) => Some((*lat, *long)),
+            None => match locate(providers, city.as_deref(), &mut self.cache, false).await {
+                Ok(location) => Some(location),
+                Err(e) => {
+                    eprintln!("warning: failed to find location: {e}");
+                    self.cache.coords
+                },
+            },
+        };
+
+        let result = match location {
+            Some((lat, long)) => get_weather(providers, lat, long)
+                .await
+                .map(|data| ((lat, long), data)),
+            None => Err("no coordinates available".into()),
+        };
+        match result {
+            Ok((coords, data)) => {
+                self.backoff.reset();
+                self.cache.update(coords, data);
+                if let Err(e) = self.cache.save() {
+                    eprintln!("warning: failed to save weather cache: {e}");
+                }
+                None
+            },
+            Err(e) => {
+                eprintln!("failed to fetch weather: {e}");
+                let delay = self.backoff.failure();
+                println!(
+                    "retrying weather in {}",
+                    humantime::format_duration(Duration::from_secs(delay.as_secs()))
+                );
+                Some(delay)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// Serve fixed json bodies by path prefix on a local port, returning the base url.
+    /// When a lowercase header line is required, requests without it are rejected as unauthorized.
+    pub async fn http_stub(
+        routes: &'static [(&'static str, &'static str)],
+        required_header: Option<&'static str>,
+    ) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match required_header {
+                    Some(header) if !request.to_lowercase().contains(header) => {
+                        ("401 Unauthorized", "")
+                    },
+                    _ => routes
+                        .iter()
+                        .find(|(prefix, _)| path.starts_with(prefix))
+                        .map_or(("404 Not Found", ""), |(_, body)| ("200 OK", body)),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\n\
+                     content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn local_endpoints() {
+        let url = http_stub(
+            &[
+                (
+                    "/json",
+                    r#"{ "ip": "127.0.0.1", "loc": "59.9139,10.7522" }"#,
+                ),
+                ("/ip", "127.0.0.1\n"),
+                ("/vpn-ip", "10.8.0.2"),
+                (
+                    "/v1/forecast?latitude=59.9139",
+                    r#"{
+                    "current": {
+                        "time": "2024-05-01T13:15", "temperature_2m": 20.0,
+                        "apparent_temperature": 18.0,
+                        "weather_code": 3, "is_day": 1
+                    },
+                    "daily": {
+                        "weather_code": [3], "temperature_2m_min": [10.0],
+                        "temperature_2m_max": [25.0], "apparent_temperature_min": [8.0],
+                        "apparent_temperature_max": [24.0]
+                    }
+                }"#,
+                ),
+            ],
+            None,
+        )
+        .await;
+        let args = ProviderArgs {
+            provider: Provider::OpenMeteo,
+            fallback_provider: None,
+            api_key: None,
+            base_url: Some(format!("{url}/")),
+            geolocation_url: format!("{url}/json"),
+            geolocation_fallbacks: Vec::new(),
+            ipinfo_token: None,
+            ip_version: IpVersion::Any,
+            ip_check_url: format!("{url}/ip"),
+            geocoding_url: GEOCODING_URL.into(),
+            proxy: None,
+            timeout: Duration::from_secs(5).into(),
+            forecast: ForecastMode::Current,
+            evening_hour: EVENING_HOUR,
+            apparent: false,
+        };
+
+        let mut cache = WeatherCache::default();
+        let (lat, long) = geolocation::get_coords(&args, &mut cache).await.unwrap();
+        assert_eq!((lat, long), (59.9139, 10.7522));
+        let data = get_weather(&args, lat, long).await.unwrap().to_fahrenheit();
+        assert_eq!(
+            (data.wmo, data.current, data.min, data.max),
+            (3, 68., 50., 77.)
+        );
+
+        // the cached geolocation is reused until the public ip changes, then the fallback is used
+        let args = ProviderArgs {
+            provider: Provider::MetNorway,
+            geolocation_url: format!("{url}/missing"),
+            ..args
+        };
+        let coords = geolocation::get_coords(&args, &mut cache).await.unwrap();
+        assert_eq!(coords, (59.9139, 10.7522));
+        let vpn = ProviderArgs {
+            ip_check_url: format!("{url}/vpn-ip"),
+            ..args.clone()
+        };
+        assert!(geolocation::get_coords(&vpn, &mut cache).await.is_err());
+        let vpn = ProviderArgs {
+            geolocation_fallbacks: vec![format!("{url}/json")],
+            ..vpn
+        };
+        assert!(geolocation::get_coords(&vpn, &mut cache).await.is_ok());
+
+        // missing endpoints and api keys are errors rather than panics
+        let mut cache = WeatherCache::default();
+        assert!(geolocation::get_coords(&args, &mut cache).await.is_err());
+        assert!(get_weather(&args, lat, long).await.is_err());
+        let args = ProviderArgs {
+            provider: Provider::OpenWeatherMap,
+            ..args
+        };
+        assert!(get_weather(&args, lat, long).await.is_err());
+    }
+}
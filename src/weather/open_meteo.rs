@@ -0,0 +1,268 @@
+//! Open-meteo forecast api, which reports WMO codes directly
+
+use chrono::NaiveDateTime;
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::forecast::{summarize, totals, Forecast, ForecastMode, Step};
+use super::{fetch, WeatherData, WeatherError, WeatherProvider};
+
+pub const BASE_URL: &str = "https://api.open-meteo.com";
+
+pub struct OpenMeteo {
+    pub client: Client,
+    pub base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    current: Option<Current>,
+    hourly: Option<Hourly>,
+    daily: Option<Daily>,
+}
+
+#[derive(Deserialize)]
+struct Current {
+    /// Local time, ie "2024-01-01T13:15"
+    time: String,
+    temperature_2m: f32,
+    apparent_temperature: Option<f32>,
+    weather_code: u8,
+    is_day: u8,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Hourly {
+    time: Vec<String>,
+    temperature_2m: Vec<Option<f32>>,
+    apparent_temperature: Vec<Option<f32>>,
+    weather_code: Vec<Option<u8>>,
+    precipitation: Vec<Option<f32>>,
+    wind_gusts_10m: Vec<Option<f32>>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Daily {
+    weather_code: Vec<Option<u8>>,
+    temperature_2m_min: Vec<Option<f32>>,
+    temperature_2m_max: Vec<Option<f32>>,
+    apparent_temperature_min: Vec<Option<f32>>,
+    apparent_temperature_max: Vec<Option<f32>>,
+    precipitation_sum: Vec<Option<f32>>,
+    wind_gusts_10m_max: Vec<Option<f32>>,
+}
+
+impl WeatherProvider for OpenMeteo {
+    fn get_weather(
+        &self,
+        lat: f32,
+        long: f32,
+        forecast: Forecast,
+    ) -> BoxFuture<'_, Result<WeatherData, WeatherError>> {
+        Box::pin(async move {
+            let mut query = vec![
+                ("latitude", lat.to_string()),
+                ("longitude", long.to_string()),
+                (
+                    "current",
+                    "temperature_2m,apparent_temperature,weather_code,is_day".into(),
+                ),
+                (
+                    "daily",
+                    "weather_code,temperature_2m_min,temperature_2m_max,\
+                     apparent_temperature_min,apparent_temperature_max,\
+                     precipitation_sum,wind_gusts_10m_max"
+                        .into(),
+                ),
+                ("timezone", "auto".into()),
+                ("wind_speed_unit", "ms".into()),
+            ];
+            let mut days = 2;
+            if let ForecastMode::Hours(hours) = forecast.mode {
+                query.push((
+                    "hourly",
+                    "temperature_2m,apparent_temperature,weather_code,precipitation,\
+                     wind_gusts_10m"
+                        .into(),
+                ));
+                // hourly data starts at midnight, so cover the rest of today plus the window
+                days += hours as usize / 24;
+            }
+            query.push(("forecast_days", days.to_string()));
+            let request = self
+                .client
+                .get(format!("{}/v1/forecast", self.base_url))
+                .query(&query);
+            parse(&fetch(request).await?, forecast)
+        })
+    }
+}
+
+fn parse(body: &str, forecast: Forecast) -> Result<WeatherData, WeatherError> {
+    let res: Response = serde_json::from_str(body)?;
+    let current = res
+        .current
+        .ok_or(WeatherError::Missing("current weather"))?;
+    let temp = match current.apparent_temperature {
+        Some(apparent) if forecast.apparent => apparent,
+        _ => current.temperature_2m,
+    };
+    // the current time is local to the location, since the request asks for its timezone
+    let local_time = NaiveDateTime::parse_from_str(&current.time, "%Y-%m-%dT%H:%M")
+        .map_err(|_| WeatherError::Missing("current time"))?
+        .time();
+    let mode = forecast.mode_at(local_time);
+
+    match mode {
+        ForecastMode::Current | ForecastMode::Tomorrow => {
+            let daily = res.daily.ok_or(WeatherError::Missing("daily forecast"))?;
+            let day = (mode == ForecastMode::Tomorrow) as usize;
+            let (min, max) = if forecast.apparent {
+                (
+                    &daily.apparent_temperature_min,
+                    &daily.apparent_temperature_max,
+                )
+            } else {
+                (&daily.temperature_2m_min, &daily.temperature_2m_max)
+            };
+            let get = |values: &[Option<f32>], name| {
+                values
+                    .get(day)
+                    .copied()
+                    .flatten()
+                    .ok_or(WeatherError::Missing(name))
+            };
+            let wmo = if day == 0 {
+                current.weather_code
+            } else {
+                daily
+                    .weather_code
+                    .get(day)
+                    .copied()
+                    .flatten()
+                    .ok_or(WeatherError::Missing("daily weather code"))?
+            };
+            Ok(WeatherData {
+                wmo,
+                // show daytime icons for tomorrow's forecast
+                is_day: day > 0 || current.is_day == 1,
+                current: temp,
+                min: get(min, "minimum temperature")?,
+                max: get(max, "maximum temperature")?,
+                precipitation: daily.precipitation_sum.get(day).copied().flatten(),
+                wind_gusts: daily.wind_gusts_10m_max.get(day).copied().flatten(),
+            })
+        },
+        ForecastMode::Hours(hours) => {
+            let hourly = res.hourly.ok_or(WeatherError::Missing("hourly forecast"))?;
+            let temps = if forecast.apparent {
+                &hourly.apparent_temperature
+            } else {
+                &hourly.temperature_2m
+            };
+            // hourly times are in the same local format, so compare them from the current hour
+            let hour = format!("{}:00", current.time.get(..13).unwrap_or(&current.time));
+            let steps: Vec<_> = hourly
+                .time
+                .iter()
+                .enumerate()
+                .filter(|(_, time)| **time >= hour)
+                .take(hours as usize)
+                .filter_map(|(i, _)| {
+                    Some(Step {
+                        wmo: hourly.weather_code.get(i).copied().flatten()?,
+                        temp: temps.get(i).copied().flatten()?,
+                        precipitation: hourly
+                            .precipitation
+                            .get(i)
+                            .copied()
+                            .flatten()
+                            .unwrap_or_default(),
+                        gust: hourly.wind_gusts_10m.get(i).copied().flatten(),
+                    })
+                })
+                .collect();
+            let (wmo, min, max) =
+                summarize(&steps).ok_or(WeatherError::Missing("hourly forecast"))?;
+            let (precipitation, wind_gusts) = totals(&steps);
+            Ok(WeatherData {
+                wmo,
+                is_day: current.is_day == 1,
+                current: temp,
+                min,
+                max,
+                precipitation,
+                wind_gusts,
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response() {
+        let body = r#"{
+            "current": {
+                "time": "2024-05-01T13:15", "temperature_2m": 12.3,
+                "apparent_temperature": 10.1, "weather_code": 61, "is_day": 0
+            },
+            "hourly": {
+                "time": ["2024-05-01T12:00", "2024-05-01T13:00", "2024-05-01T14:00"],
+                "temperature_2m": [11.0, 12.0, 14.0],
+                "apparent_temperature": [9.0, 10.0, 12.0],
+                "weather_code": [3, 61, 95],
+                "precipitation": [0.0, 0.2, 1.5],
+                "wind_gusts_10m": [8.0, 9.5, 21.0]
+            },
+            "daily": {
+                "weather_code": [61, 3],
+                "temperature_2m_min": [8.1, 6.0], "temperature_2m_max": [15.0, 18.0],
+                "apparent_temperature_min": [6.0, 4.0], "apparent_temperature_max": [13.0, 16.0],
+                "precipitation_sum": [4.2, 0.0]
+            }
+        }"#;
+        let parse = |mode, apparent| {
+            let forecast = Forecast {
+                mode,
+                apparent,
+                ..Default::default()
+            };
+            parse(body, forecast).unwrap()
+        };
+        assert_eq!(
+            parse(ForecastMode::Current, false),
+            WeatherData {
+                wmo: 61,
+                is_day: false,
+                current: 12.3,
+                min: 8.1,
+                max: 15.,
+                precipitation: Some(4.2),
+                wind_gusts: None,
+            }
+        );
+        let tomorrow = parse(ForecastMode::Tomorrow, true);
+        assert_eq!(
+            (tomorrow.wmo, tomorrow.is_day, tomorrow.min, tomorrow.max),
+            (3, true, 4., 16.)
+        );
+        let hours = parse(ForecastMode::Hours(2), false);
+        assert_eq!((hours.wmo, hours.min, hours.max), (95, 12., 14.));
+        assert_eq!(
+            (hours.precipitation, hours.wind_gusts),
+            (Some(1.7), Some(21.))
+        );
+
+        let missing = r#"{ "daily": { "temperature_2m_min": [null], "temperature_2m_max": [] } }"#;
+        assert!(matches!(
+            super::parse(missing, Forecast::default()),
+            Err(WeatherError::Missing(_))
+        ));
+    }
+}
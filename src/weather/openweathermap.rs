@@ -0,0 +1,347 @@
+//! OpenWeatherMap current weather and 5 day forecast apis, requiring an api key
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::forecast::{summarize, totals, Forecast, ForecastMode, Step};
+use super::{fetch, WeatherData, WeatherError, WeatherProvider};
+
+pub const BASE_URL: &str = "https://api.openweathermap.org";
+
+/// The forecast api reports steps every 3 hours
+const STEP_SECONDS: i64 = 3 * 3600;
+
+pub struct OpenWeatherMap {
+    pub client: Client,
+    pub base_url: String,
+    pub api_key: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    weather: Vec<Condition>,
+    main: Main,
+    wind: Option<Wind>,
+    rain: Option<Volume>,
+    snow: Option<Volume>,
+    /// Unix time of the observation
+    dt: i64,
+    /// Utc offset of the location in seconds
+    timezone: i32,
+}
+
+/// 5 day forecast in 3 hour steps
+#[derive(Deserialize)]
+struct ForecastResponse {
+    list: Vec<ForecastStep>,
+}
+
+#[derive(Deserialize)]
+struct ForecastStep {
+    /// Unix time of the end of the step
+    dt: i64,
+    main: StepMain,
+    #[serde(default)]
+    weather: Vec<Condition>,
+    wind: Option<Wind>,
+    rain: Option<StepVolume>,
+    snow: Option<StepVolume>,
+}
+
+#[derive(Deserialize)]
+struct StepVolume {
+    /// Precipitation in mm over the 3 hours up to the step
+    #[serde(rename = "3h")]
+    three_hours: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct StepMain {
+    temp: f32,
+    feels_like: f32,
+}
+
+#[derive(Deserialize)]
+struct Condition {
+    id: u16,
+    /// Icon id, ie "10d" or "10n"
+    icon: String,
+}
+
+#[derive(Deserialize)]
+struct Main {
+    temp: f32,
+    feels_like: f32,
+}
+
+#[derive(Deserialize)]
+struct Wind {
+    /// Gust speed in m/s with metric units
+    gust: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Volume {
+    /// Precipitation in mm over the last hour
+    #[serde(rename = "1h")]
+    one_hour: Option<f32>,
+}
+
+impl WeatherProvider for OpenWeatherMap {
+    fn get_weather(
+        &self,
+        lat: f32,
+        long: f32,
+        forecast: Forecast,
+    ) -> BoxFuture<'_, Result<WeatherData, WeatherError>> {
+        Box::pin(async move {
+            let request = |endpoint: &str| {
+                self.client
+                    .get(format!("{}/data/2.5/{endpoint}", self.base_url))
+                    .query(&[
+                        ("lat", lat.to_string()),
+                        ("lon", long.to_string()),
+                        ("units", "metric".into()),
+                        ("appid", self.api_key.clone()),
+                    ])
+            };
+            let (current, steps) =
+                futures::future::try_join(fetch(request("weather")), fetch(request("forecast")))
+                    .await?;
+            parse(&current, &steps, forecast)
+        })
+    }
+}
+
+/// Local date of a unix time at a utc offset
+fn local_date(time: i64, offset: &FixedOffset) -> Option<NaiveDate> {
+    DateTime::from_timestamp(time, 0).map(|t| t.with_timezone(offset).date_naive())
+}
+
+impl ForecastStep {
+    /// Convert a step for summarizing, skipping unknown conditions
+    fn step(&self, apparent: bool) -> Option<Step> {
+        Some(Step {
+            wmo: condition_to_wmo(self.weather.first()?.id)?,
+            temp: temp(&self.main, apparent),
+            precipitation: [&self.rain, &self.snow]
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.three_hours)
+                .sum(),
+            gust: self.wind.as_ref().and_then(|w| w.gust),
+        })
+    }
+}
+
+fn temp(main: &StepMain, apparent: bool) -> f32 {
+    if apparent {
+        main.feels_like
+    } else {
+        main.temp
+    }
+}
+
+/// Parse the current weather, with today's range from the current temperature and the rest of
+/// the day's forecast, or summarize the 3 hour forecast steps for the forecast modes
+fn parse(body: &str, forecast_body: &str, forecast: Forecast) -> Result<WeatherData, WeatherError> {
+    let res: Response = serde_json::from_str(body)?;
+    let steps: ForecastResponse = serde_json::from_str(forecast_body)?;
+    let condition = res
+        .weather
+        .first()
+        .ok_or(WeatherError::Missing("weather condition"))?;
+    let current = if forecast.apparent {
+        res.main.feels_like
+    } else {
+        res.main.temp
+    };
+    let is_day = !condition.icon.ends_with('n');
+    let offset = FixedOffset::east_opt(res.timezone).ok_or(WeatherError::Missing("timezone"))?;
+    let today = local_date(res.dt, &offset);
+    let local_time = DateTime::from_timestamp(res.dt, 0)
+        .ok_or(WeatherError::Missing("observation time"))?
+        .with_timezone(&offset)
+        .time();
+
+    let summary = |steps: Vec<Step>, is_day| -> Result<WeatherData, WeatherError> {
+        let (wmo, min, max) = summarize(&steps).ok_or(WeatherError::Missing("forecast steps"))?;
+        let (precipitation, wind_gusts) = totals(&steps);
+        Ok(WeatherData {
+            wmo,
+            is_day,
+            current,
+            min,
+            max,
+            precipitation,
+            wind_gusts,
+        })
+    };
+    match forecast.mode_at(local_time) {
+        ForecastMode::Current => {},
+        ForecastMode::Hours(hours) => {
+            // every step ending after now, and starting before the end of the window
+            let end = res.dt + i64::from(hours) * 3600;
+            let steps = steps
+                .list
+                .iter()
+                .filter(|s| s.dt > res.dt && s.dt - STEP_SECONDS < end)
+                .filter_map(|s| s.step(forecast.apparent))
+                .collect();
+            return summary(steps, is_day);
+        },
+        ForecastMode::Tomorrow => {
+            let tomorrow = today.and_then(|d| d.succ_opt());
+            let steps = steps
+                .list
+                .iter()
+                .filter(|s| local_date(s.dt, &offset) == tomorrow)
+                .filter_map(|s| s.step(forecast.apparent))
+                .collect();
+            // show daytime icons for tomorrow's forecast
+            return summary(steps, true);
+        },
+    }
+
+    let temps = steps
+        .list
+        .iter()
+        .filter(|step| local_date(step.dt, &offset) == today)
+        .map(|step| temp(&step.main, forecast.apparent))
+        .chain([current]);
+    let min = temps.clone().fold(f32::INFINITY, f32::min);
+    let max = temps.fold(f32::NEG_INFINITY, f32::max);
+
+    Ok(WeatherData {
+        wmo: condition_to_wmo(condition.id)
+            .ok_or_else(|| WeatherError::UnknownCondition(condition.id.to_string()))?,
+        is_day,
+        current,
+        min,
+        max,
+        precipitation: [&res.rain, &res.snow]
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.one_hour)
+            .reduce(|a, b| a + b),
+        wind_gusts: res.wind.and_then(|w| w.gust),
+    })
+}
+
+/// Map an OpenWeatherMap condition id to a WMO code
+fn condition_to_wmo(id: u16) -> Option<u8> {
+    Some(match id {
+        // thunderstorm
+        200..=232 => 95,
+        // drizzle
+        300 | 310 => 51,
+        301 | 311 | 313 | 321 => 53,
+        302 | 312 | 314 => 55,
+        // rain
+        500 => 61,
+        501 => 63,
+        502..=504 => 65,
+        511 => 66,
+        520 => 80,
+        521 => 81,
+        522 | 531 => 82,
+        // snow
+        600 => 71,
+        601 => 73,
+        602 => 75,
+        611 | 612 | 615 => 66,
+        613 | 616 => 67,
+        620 | 621 => 85,
+        622 => 86,
+        // atmosphere (mist, smoke, haze, dust, fog, ...)
+        701..=781 => 45,
+        // clear and clouds
+        800 => 0,
+        801 => 1,
+        802 => 2,
+        803 | 804 => 3,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response() {
+        // 2024-05-01 18:00 in utc+2
+        let body = r#"{
+            "weather": [{ "id": 521, "main": "Rain", "icon": "09n" }],
+            "main": { "temp": 9.8, "feels_like": 7.2, "temp_min": 8.9, "temp_max": 10.6 },
+            "wind": { "speed": 6.1, "gust": 11.3 },
+            "rain": { "1h": 2.73 },
+            "dt": 1714579200,
+            "timezone": 7200
+        }"#;
+        // steps at 20:00 and 23:00 today, and 02:00 tomorrow
+        let forecast = r#"{
+            "list": [
+                { "dt": 1714586400, "main": { "temp": 8.1, "feels_like": 5.9 },
+                  "weather": [{ "id": 500, "icon": "10n" }], "rain": { "3h": 0.6 },
+                  "wind": { "speed": 5.2, "gust": 14.2 } },
+                { "dt": 1714597200, "main": { "temp": 6.4, "feels_like": 4.0 },
+                  "weather": [{ "id": 803, "icon": "04n" }] },
+                { "dt": 1714608000, "main": { "temp": 3.2, "feels_like": 1.1 },
+                  "weather": [{ "id": 800, "icon": "01n" }] }
+            ]
+        }"#;
+        let mode = |mode| Forecast {
+            mode,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse(body, forecast, Forecast::default()).unwrap(),
+            WeatherData {
+                wmo: 81,
+                is_day: false,
+                current: 9.8,
+                min: 6.4,
+                max: 9.8,
+                precipitation: Some(2.73),
+                wind_gusts: Some(11.3),
+            }
+        );
+        let apparent = Forecast {
+            apparent: true,
+            ..Default::default()
+        };
+        let apparent = parse(body, forecast, apparent).unwrap();
+        assert_eq!((apparent.current, apparent.min), (7.2, 4.0));
+
+        // the next 2 hours only overlap the step ending at 20:00
+        assert_eq!(
+            parse(body, forecast, mode(ForecastMode::Hours(2))).unwrap(),
+            WeatherData {
+                wmo: 61,
+                is_day: false,
+                current: 9.8,
+                min: 8.1,
+                max: 8.1,
+                precipitation: Some(0.6),
+                wind_gusts: Some(14.2),
+            }
+        );
+        assert_eq!(
+            parse(body, forecast, mode(ForecastMode::Tomorrow)).unwrap(),
+            WeatherData {
+                wmo: 0,
+                is_day: true,
+                current: 9.8,
+                min: 3.2,
+                max: 3.2,
+                precipitation: Some(0.),
+                wind_gusts: None,
+            }
+        );
+        assert_eq!(condition_to_wmo(804), Some(3));
+        assert_eq!(condition_to_wmo(999), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! Rotation between the weather of several named locations
+
+use std::error::Error;
+use std::time::Duration;
+
+use zoom_sync_core::Board;
+
+use super::{WeatherData, WeatherUpdater};
+use crate::sources::WeatherOverrides;
+use crate::temperature::RangeConfig;
+
+/// Weather updates for one or more locations, showing one of them at a time
+pub struct WeatherRotation {
+    updaters: Vec<WeatherUpdater>,
+    active: usize,
+}
+
+impl WeatherRotation {
+    pub fn new(updaters: Vec<WeatherUpdater>) -> Self {
+        assert!(!updaters.is_empty(), "weather rotation needs a location");
+        Self {
+            updaters,
+            active: 0,
+        }
+    }
+
+    /// The location currently shown on the board
+    pub fn active(&self) -> &WeatherUpdater {
+        &self.updaters[self.active]
+    }
+
+    /// Check if there are several locations to rotate between
+    pub fn is_rotating(&self) -> bool {
+        self.updaters.len() > 1
+    }
+
+    /// Last fetched weather for each location
+    pub fn data(&self) -> impl Iterator<Item = (Option<&str>, &WeatherData)> {
+        self.updaters
+            .iter()
+            .filter_map(|u| Some((u.name.as_deref(), u.data()?)))
+    }
+
+    /// Fetch the weather for every location, and push the active one. When fetching fails for
+    /// any location, the shortest delay before the next attempt is returned.
+    pub async fn update(
+        &mut self,
+        board: &mut dyn Board,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        overrides: WeatherOverrides,
+    ) -> Result<Option<Duration>, Box<dyn Error>> {
+        let mut retry = None;
+        for (i, updater) in self.updaters.iter_mut().enumerate() {
+            let delay = if i == self.active {
+                updater.update(board, fahrenheit, ranges, overrides).await?
+            } else {
+                updater.refresh().await
+            };
+            retry = match (retry, delay) {
+                (Some(a), Some(b)) => Some(b.min(a)),
+                (a, b) => a.or(b),
+            };
+        }
+        Ok(retry)
+    }
+
+    /// Push the cached weather of the active location, without fetching
+    pub fn apply_cached(
+        &self,
+        board: &mut dyn Board,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        overrides: WeatherOverrides,
+    ) -> Result<(), Box<dyn Error>> {
+        self.active()
+            .apply_cached(board, fahrenheit, ranges, overrides)
+    }
+
+    /// Switch to the next location with any weather to show, and push its cached weather
+    pub fn rotate(
+        &mut self,
+        board: &mut dyn Board,
+        fahrenheit: bool,
+        ranges: &RangeConfig,
+        overrides: WeatherOverrides,
+    ) -> Result<(), Box<dyn Error>> {
+        let len = self.updaters.len();
+        let Some(next) = (1..len)
+            .map(|offset| (self.active + offset) % len)
+            .find(|i| self.updaters[*i].data().is_some())
+        else {
+            return Ok(());
+        };
+        self.active = next;
+        if let Some(name) = &self.active().name {
+            println!("showing weather for {name}");
+        }
+        self.apply_cached(board, fahrenheit, ranges, overrides)
+    }
+
+    /// Time until the next sunrise or sunset at the active location
+    pub fn until_sun_transition(&self) -> Option<Duration> {
+        self.active().until_sun_transition()
+    }
+}
@@ -0,0 +1,158 @@
+//! Local sunrise and sunset calculation, for day/night weather icons without a network request
+
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Days, NaiveDate, TimeDelta, Utc};
+
+/// Julian date of the J2000 epoch, 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.;
+/// Julian date of the unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+/// Solar elevation at sunrise and sunset, accounting for refraction and the solar disc
+const HORIZON: f64 = -0.833;
+/// Axial tilt of the earth
+const OBLIQUITY: f64 = 23.4397;
+
+/// Sunrise and sunset for a single solar day
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SunTimes {
+    Rises {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    /// Midnight sun
+    AlwaysUp,
+    /// Polar night
+    AlwaysDown,
+}
+
+/// Compute sunrise and sunset for the solar day closest to the given date, using the sunrise
+/// equation. Accurate to within a couple of minutes outside of the polar regions.
+pub fn sun_times(date: NaiveDate, lat: f32, long: f32) -> SunTimes {
+    let (lat, long) = (lat as f64, long as f64);
+    let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let days = (date - j2000).num_days() as f64;
+
+    // mean solar time, solar mean anomaly, and equation of the center
+    let mean_time = days - long / 360.;
+    let anomaly = (357.5291 + 0.98560028 * mean_time).rem_euclid(360.);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2. * m).sin() + 0.0003 * (3. * m).sin();
+
+    // ecliptic longitude, solar transit, and declination
+    let ecliptic = (anomaly + center + 180. + 102.9372)
+        .rem_euclid(360.)
+        .to_radians();
+    let transit = J2000 + mean_time + 0.0053 * m.sin() - 0.0069 * (2. * ecliptic).sin();
+    let declination = (ecliptic.sin() * OBLIQUITY.to_radians().sin()).asin();
+
+    // hour angle between the transit and the sun crossing the horizon
+    let lat = lat.to_radians();
+    let cos_hour_angle = (HORIZON.to_radians().sin() - lat.sin() * declination.sin())
+        / (lat.cos() * declination.cos());
+    if cos_hour_angle > 1. {
+        return SunTimes::AlwaysDown;
+    }
+    if cos_hour_angle < -1. {
+        return SunTimes::AlwaysUp;
+    }
+    let hour_angle = cos_hour_angle.acos() / (2. * PI);
+
+    SunTimes::Rises {
+        sunrise: from_julian(transit - hour_angle),
+        sunset: from_julian(transit + hour_angle),
+    }
+}
+
+fn from_julian(date: f64) -> DateTime<Utc> {
+    let seconds = ((date - UNIX_EPOCH_JD) * 86400.).round() as i64;
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
+
+/// The solar days around a time, since a solar day can span two utc dates far from greenwich
+fn days_around(time: DateTime<Utc>, lat: f32, long: f32) -> impl Iterator<Item = SunTimes> {
+    let date = time.date_naive();
+    [
+        date.checked_sub_days(Days::new(1)),
+        Some(date),
+        date.succ_opt(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(move |date| sun_times(date, lat, long))
+}
+
+/// Check if the sun is up at a given time and location
+pub fn is_day(time: DateTime<Utc>, lat: f32, long: f32) -> bool {
+    match sun_times(time.date_naive(), lat, long) {
+        SunTimes::AlwaysUp => true,
+        SunTimes::AlwaysDown => false,
+        SunTimes::Rises { .. } => days_around(time, lat, long).any(|times| {
+            matches!(times, SunTimes::Rises { sunrise, sunset } if sunrise <= time && time < sunset)
+        }),
+    }
+}
+
+/// Find the next sunrise or sunset after a given time, if there is one within a day or so
+pub fn next_transition(time: DateTime<Utc>, lat: f32, long: f32) -> Option<DateTime<Utc>> {
+    days_around(time + TimeDelta::days(1), lat, long)
+        .chain(days_around(time, lat, long))
+        .flat_map(|times| match times {
+            SunTimes::Rises { sunrise, sunset } => vec![sunrise, sunset],
+            _ => vec![],
+        })
+        .filter(|t| *t > time)
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn berlin_winter() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let SunTimes::Rises { sunrise, sunset } = sun_times(date, 52.52, 13.405) else {
+            panic!("sun should rise in berlin");
+        };
+        // 08:14 and 16:13 local time (CET)
+        assert!((sunrise - utc("2024-01-10T07:14:00Z")).num_minutes().abs() <= 3);
+        assert!((sunset - utc("2024-01-10T15:13:00Z")).num_minutes().abs() <= 3);
+
+        assert!(is_day(utc("2024-01-10T12:00:00Z"), 52.52, 13.405));
+        // 6pm local is dark in january, unlike the old fixed 6-18 range
+        assert!(!is_day(utc("2024-01-10T17:00:00Z"), 52.52, 13.405));
+        assert_eq!(
+            next_transition(utc("2024-01-10T12:00:00Z"), 52.52, 13.405),
+            Some(sunset)
+        );
+    }
+
+    #[test]
+    fn far_from_greenwich() {
+        // noon in auckland is around midnight utc
+        assert!(is_day(utc("2024-03-01T23:00:00Z"), -36.85, 174.76));
+        assert!(!is_day(utc("2024-03-01T11:00:00Z"), -36.85, 174.76));
+        // sunset in honolulu is after midnight utc
+        let sunset = next_transition(utc("2024-03-01T22:00:00Z"), 21.31, -157.86).unwrap();
+        assert!((sunset - utc("2024-03-02T04:40:00Z")).num_minutes().abs() <= 5);
+    }
+
+    #[test]
+    fn polar() {
+        let (lat, long) = (69.65, 18.96);
+        let midsummer = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let midwinter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(sun_times(midsummer, lat, long), SunTimes::AlwaysUp);
+        assert_eq!(sun_times(midwinter, lat, long), SunTimes::AlwaysDown);
+        assert!(!is_day(utc("2024-12-21T11:00:00Z"), lat, long));
+        assert_eq!(
+            next_transition(utc("2024-12-21T11:00:00Z"), lat, long),
+            None
+        );
+    }
+}
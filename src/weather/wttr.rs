@@ -0,0 +1,371 @@
+//! wttr.in json api, which reports WorldWeatherOnline condition codes
+
+use chrono::{NaiveDateTime, NaiveTime, Timelike, Utc};
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::forecast::{solar_offset, summarize, totals, Forecast, ForecastMode, Step};
+use super::{fetch, is_daytime, WeatherData, WeatherError, WeatherProvider};
+
+pub const BASE_URL: &str = "https://wttr.in";
+
+pub struct Wttr {
+    pub client: Client,
+    pub base_url: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    current_condition: Vec<CurrentCondition>,
+    weather: Vec<Day>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurrentCondition {
+    #[serde(rename = "temp_C")]
+    temp_c: String,
+    #[serde(rename = "FeelsLikeC")]
+    feels_like_c: String,
+    weather_code: String,
+    /// Local observation time, ie "2024-05-10 09:13 AM"
+    local_obs_date_time: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Day {
+    #[serde(rename = "mintempC")]
+    min_temp_c: String,
+    #[serde(rename = "maxtempC")]
+    max_temp_c: String,
+    #[serde(default)]
+    astronomy: Vec<Astronomy>,
+    #[serde(default)]
+    hourly: Vec<Hourly>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hourly {
+    /// Local time of day as hours and minutes, ie "0", "300", or "2100"
+    time: String,
+    #[serde(rename = "tempC")]
+    temp_c: String,
+    #[serde(rename = "FeelsLikeC")]
+    feels_like_c: String,
+    weather_code: String,
+    #[serde(rename = "precipMM")]
+    precip_mm: String,
+    #[serde(rename = "WindGustKmph")]
+    wind_gust_kmph: Option<String>,
+}
+
+/// wttr.in reports hourly forecasts in 3 hour steps
+const STEP_HOURS: u32 = 3;
+
+#[derive(Deserialize)]
+struct Astronomy {
+    /// Local time, ie "05:43 AM"
+    sunrise: String,
+    sunset: String,
+}
+
+impl WeatherProvider for Wttr {
+    fn get_weather(
+        &self,
+        lat: f32,
+        long: f32,
+        forecast: Forecast,
+    ) -> BoxFuture<'_, Result<WeatherData, WeatherError>> {
+        Box::pin(async move {
+            let request = self
+                .client
+                .get(format!("{}/{lat},{long}", self.base_url))
+                .query(&[("format", "j1")]);
+            parse(&fetch(request).await?, forecast, (lat, long))
+        })
+    }
+}
+
+fn temp(value: &str, name: &'static str) -> Result<f32, WeatherError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| WeatherError::Missing(name))
+}
+
+fn wmo(code: &str) -> Result<u8, WeatherError> {
+    code.trim()
+        .parse()
+        .ok()
+        .and_then(code_to_wmo)
+        .ok_or_else(|| WeatherError::UnknownCondition(code.to_string()))
+}
+
+impl Hourly {
+    /// Hour of the day this step starts at
+    fn hour(&self) -> Option<u32> {
+        self.time.trim().parse::<u32>().ok().map(|t| t / 100)
+    }
+
+    fn step(&self, apparent: bool) -> Result<Step, WeatherError> {
+        Ok(Step {
+            wmo: wmo(&self.weather_code)?,
+            temp: if apparent {
+                temp(&self.feels_like_c, "hourly apparent temperature")?
+            } else {
+                temp(&self.temp_c, "hourly temperature")?
+            },
+            precipitation: self.precip_mm.trim().parse().unwrap_or_default(),
+            gust: self
+                .wind_gust_kmph
+                .as_deref()
+                .and_then(|kmph| kmph.trim().parse::<f32>().ok())
+                .map(|kmph| kmph / 3.6),
+        })
+    }
+}
+
+fn parse(body: &str, forecast: Forecast, coords: (f32, f32)) -> Result<WeatherData, WeatherError> {
+    let res: Response = serde_json::from_str(body)?;
+    let current = res
+        .current_condition
+        .first()
+        .ok_or(WeatherError::Missing("current condition"))?;
+    let today = res
+        .weather
+        .first()
+        .ok_or(WeatherError::Missing("forecast"))?;
+    let observed = current
+        .local_obs_date_time
+        .as_deref()
+        .and_then(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %I:%M %p").ok())
+        .map(|time| time.time());
+    let current_temp = if forecast.apparent {
+        temp(&current.feels_like_c, "current apparent temperature")?
+    } else {
+        temp(&current.temp_c, "current temperature")?
+    };
+
+    // approximate the local time from the longitude without an observation time
+    let local_time =
+        observed.unwrap_or_else(|| Utc::now().with_timezone(&solar_offset(coords.1)).time());
+
+    match forecast.mode_at(local_time) {
+        ForecastMode::Current => {},
+        ForecastMode::Hours(hours) => {
+            // include every 3 hour step overlapping the window, starting from the observation
+            let start = local_time.hour();
+            let end = start + hours as u32;
+            let mut steps = Vec::new();
+            for (day, forecast_day) in (0..).zip(&res.weather) {
+                for hourly in &forecast_day.hourly {
+                    let Some(hour) = hourly.hour().map(|h| day * 24 + h) else {
+                        continue;
+                    };
+                    if hour + STEP_HOURS > start && hour < end {
+                        steps.push(hourly.step(forecast.apparent)?);
+                    }
+                }
+            }
+            let (wmo, min, max) =
+                summarize(&steps).ok_or(WeatherError::Missing("hourly forecast"))?;
+            let (precipitation, wind_gusts) = totals(&steps);
+            return Ok(WeatherData {
+                wmo,
+                is_day: is_day(observed, today).unwrap_or_else(|| is_daytime(Some(coords))),
+                current: current_temp,
+                min,
+                max,
+                precipitation,
+                wind_gusts,
+            });
+        },
+        ForecastMode::Tomorrow => {
+            let tomorrow = res
+                .weather
+                .get(1)
+                .ok_or(WeatherError::Missing("tomorrow's forecast"))?;
+            let steps = tomorrow
+                .hourly
+                .iter()
+                .map(|h| h.step(forecast.apparent))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (wmo, hourly_min, hourly_max) =
+                summarize(&steps).ok_or(WeatherError::Missing("hourly forecast"))?;
+            let (min, max) = if forecast.apparent {
+                (hourly_min, hourly_max)
+            } else {
+                (
+                    temp(&tomorrow.min_temp_c, "minimum temperature")?,
+                    temp(&tomorrow.max_temp_c, "maximum temperature")?,
+                )
+            };
+            let (precipitation, wind_gusts) = totals(&steps);
+            return Ok(WeatherData {
+                wmo,
+                // show daytime icons for tomorrow's forecast
+                is_day: true,
+                current: current_temp,
+                min,
+                max,
+                precipitation,
+                wind_gusts,
+            });
+        },
+    }
+
+    let steps: Vec<_> = today
+        .hourly
+        .iter()
+        .filter_map(|h| h.step(forecast.apparent).ok())
+        .collect();
+    let (precipitation, wind_gusts) = totals(&steps);
+    Ok(WeatherData {
+        wmo: wmo(&current.weather_code)?,
+        is_day: is_day(observed, today).unwrap_or_else(|| is_daytime(Some(coords))),
+        current: current_temp,
+        min: temp(&today.min_temp_c, "minimum temperature")?,
+        max: temp(&today.max_temp_c, "maximum temperature")?,
+        precipitation,
+        wind_gusts,
+    })
+}
+
+/// Check if the observation time is between today's sunrise and sunset
+fn is_day(observed: Option<NaiveTime>, today: &Day) -> Option<bool> {
+    observed
+        .zip(today.astronomy.first())
+        .and_then(|(time, astronomy)| {
+            let sunrise = NaiveTime::parse_from_str(&astronomy.sunrise, "%I:%M %p").ok()?;
+            let sunset = NaiveTime::parse_from_str(&astronomy.sunset, "%I:%M %p").ok()?;
+            Some(sunrise <= time && time < sunset)
+        })
+}
+
+/// Map a WorldWeatherOnline condition code to a WMO code
+fn code_to_wmo(code: u16) -> Option<u8> {
+    Some(match code {
+        // sunny/clear, partly cloudy, cloudy, overcast
+        113 => 0,
+        116 => 2,
+        119 | 122 => 3,
+        // mist, fog, freezing fog
+        143 | 248 => 45,
+        260 => 48,
+        // patchy rain, snow, sleet, and freezing drizzle
+        176 => 80,
+        179 => 85,
+        182 => 66,
+        185 | 281 => 56,
+        284 => 57,
+        // blowing snow, blizzard
+        227 => 73,
+        230 => 75,
+        // drizzle
+        263 => 51,
+        266 => 53,
+        // rain
+        293 | 296 => 61,
+        299 | 302 => 63,
+        305 | 308 => 65,
+        // freezing rain and sleet
+        311 | 317 | 362 => 66,
+        314 | 320 | 365 => 67,
+        // snow
+        323 | 326 => 71,
+        329 | 332 => 73,
+        335 | 338 => 75,
+        // ice pellets
+        350 | 374 | 377 => 77,
+        // showers
+        353 => 80,
+        356 => 81,
+        359 => 82,
+        368 => 85,
+        371 => 86,
+        // thunder
+        200 | 386 | 389 | 392 | 395 => 95,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COORDS: (f32, f32) = (59.91, 10.75);
+
+    #[test]
+    fn parse_response() {
+        let body = r#"{
+            "current_condition": [{
+                "temp_C": "-2", "FeelsLikeC": "-6", "weatherCode": "326",
+                "localObsDateTime": "2024-01-10 07:30 PM"
+            }],
+            "weather": [{
+                "mintempC": "-5", "maxtempC": "1",
+                "astronomy": [{ "sunrise": "07:45 AM", "sunset": "04:30 PM" }],
+                "hourly": [
+                    { "time": "1800", "tempC": "-2", "FeelsLikeC": "-6",
+                      "weatherCode": "326", "precipMM": "0.4" },
+                    { "time": "2100", "tempC": "-4", "FeelsLikeC": "-8",
+                      "weatherCode": "338", "precipMM": "1.1", "WindGustKmph": "36" }
+                ]
+            }, {
+                "mintempC": "-7", "maxtempC": "-1",
+                "hourly": [
+                    { "time": "0", "tempC": "-7", "FeelsLikeC": "-12",
+                      "weatherCode": "113", "precipMM": "0.0" },
+                    { "time": "1200", "tempC": "-1", "FeelsLikeC": "-4",
+                      "weatherCode": "116", "precipMM": "0.0" }
+                ]
+            }]
+        }"#;
+        let forecast = |mode, apparent| Forecast {
+            mode,
+            apparent,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse(body, forecast(ForecastMode::Hours(3), false), COORDS).unwrap(),
+            WeatherData {
+                wmo: 75,
+                is_day: false,
+                current: -2.,
+                min: -4.,
+                max: -2.,
+                precipitation: Some(1.5),
+                wind_gusts: Some(10.),
+            }
+        );
+        assert_eq!(
+            parse(body, forecast(ForecastMode::Tomorrow, true), COORDS).unwrap(),
+            WeatherData {
+                wmo: 2,
+                is_day: true,
+                current: -6.,
+                min: -12.,
+                max: -4.,
+                precipitation: Some(0.),
+                wind_gusts: None,
+            }
+        );
+        assert_eq!(
+            parse(body, Forecast::default(), COORDS).unwrap(),
+            WeatherData {
+                wmo: 71,
+                is_day: false,
+                current: -2.,
+                min: -5.,
+                max: 1.,
+                precipitation: Some(1.5),
+                wind_gusts: Some(10.),
+            }
+        );
+        assert_eq!(code_to_wmo(113), Some(0));
+        assert_eq!(code_to_wmo(1), None);
+    }
+}